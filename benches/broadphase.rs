@@ -0,0 +1,103 @@
+//! Compares the rock spatial hash against the nested loops the collision
+//! systems used before it. Run with `cargo bench --bench broadphase`.
+
+#[path = "../src/spatial_hash.rs"]
+// Its unit tests are run with the game, here they compile to nothing.
+#[allow(unused_imports)]
+mod spatial_hash;
+
+use std::time::{Duration, Instant};
+
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+use rand::prelude::*;
+
+use spatial_hash::{SpatialHash, SPATIAL_HASH_CELL_SIZE};
+
+const ARENA_SIZE: f32 = 900.0;
+const BULLET_SIZE: Vec2 = Vec2::new(4.0, 16.0);
+const ROCK_SIZE: f32 = 64.0;
+const ITERATIONS: u32 = 200;
+
+fn random_positions(rng: &mut StdRng, count: usize) -> Vec<Vec3> {
+    (0..count)
+        .map(|_| {
+            Vec3::new(
+                rng.gen::<f32>() * ARENA_SIZE,
+                rng.gen::<f32>() * ARENA_SIZE,
+                0.0,
+            )
+        })
+        .collect()
+}
+
+fn nested_loops(bullets: &[Vec3], rocks: &[Vec3]) -> usize {
+    let mut hits = 0;
+    for bullet in bullets {
+        for rock in rocks {
+            if collide(*bullet, BULLET_SIZE, *rock, Vec2::splat(ROCK_SIZE)).is_some() {
+                hits += 1;
+            }
+        }
+    }
+    hits
+}
+
+fn spatial_hash(hash: &mut SpatialHash, bullets: &[Vec3], rocks: &[Vec3]) -> usize {
+    hash.clear();
+    for (index, rock) in rocks.iter().enumerate() {
+        hash.insert(
+            Entity::from_raw(index as u32),
+            rock.truncate(),
+            ROCK_SIZE / 2.0,
+        );
+    }
+
+    let mut hits = 0;
+    let mut candidates = Vec::new();
+    for bullet in bullets {
+        hash.query(bullet.truncate(), BULLET_SIZE.y / 2.0, &mut candidates);
+        for candidate in candidates.iter() {
+            let rock = rocks[candidate.index() as usize];
+            if collide(*bullet, BULLET_SIZE, rock, Vec2::splat(ROCK_SIZE)).is_some() {
+                hits += 1;
+            }
+        }
+    }
+    hits
+}
+
+fn time<F: FnMut() -> usize>(mut f: F) -> (Duration, usize) {
+    let start = Instant::now();
+    let mut hits = 0;
+    for _ in 0..ITERATIONS {
+        hits = f();
+    }
+    (start.elapsed() / ITERATIONS, hits)
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(29);
+    let mut hash = SpatialHash::new(ARENA_SIZE, ARENA_SIZE, SPATIAL_HASH_CELL_SIZE);
+
+    println!(
+        "{:>8} {:>8} {:>14} {:>14} {:>8}",
+        "bullets", "rocks", "nested loops", "spatial hash", "speedup"
+    );
+    for (bullet_count, rock_count) in [(10, 20), (50, 100), (200, 300), (500, 1000), (1000, 2000)] {
+        let bullets = random_positions(&mut rng, bullet_count);
+        let rocks = random_positions(&mut rng, rock_count);
+
+        let (nested_time, nested_hits) = time(|| nested_loops(&bullets, &rocks));
+        let (hash_time, hash_hits) = time(|| spatial_hash(&mut hash, &bullets, &rocks));
+        assert_eq!(nested_hits, hash_hits);
+
+        println!(
+            "{:>8} {:>8} {:>14?} {:>14?} {:>7.1}x",
+            bullet_count,
+            rock_count,
+            nested_time,
+            hash_time,
+            nested_time.as_secs_f64() / hash_time.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationMode {
+    /// Starts over from the first frame after the last one.
+    Loop,
+    /// Stops on the last frame and reports itself finished.
+    Once,
+}
+
+/// Steps a `TextureAtlasSprite` through the frames `first..=last` of its atlas.
+#[derive(Component)]
+pub struct SpriteAnimation {
+    pub first: usize,
+    pub last: usize,
+    pub mode: AnimationMode,
+    frame_timer: Timer,
+    finished: bool,
+}
+
+impl SpriteAnimation {
+    pub fn new(first: usize, last: usize, frame_seconds: f32, mode: AnimationMode) -> Self {
+        SpriteAnimation {
+            first,
+            last,
+            mode,
+            frame_timer: Timer::from_seconds(frame_seconds, TimerMode::Repeating),
+            finished: false,
+        }
+    }
+
+    /// Whether a one-shot animation has shown its last frame for a full frame.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn next_frame(&mut self, index: usize) -> usize {
+        if index < self.first || index >= self.last {
+            match self.mode {
+                AnimationMode::Loop => self.first,
+                AnimationMode::Once => {
+                    self.finished = true;
+                    self.last
+                }
+            }
+        } else {
+            index + 1
+        }
+    }
+}
+
+pub fn animate_sprites(
+    mut animation_query: Query<(&mut SpriteAnimation, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+) {
+    for (mut animation, mut sprite) in animation_query.iter_mut() {
+        if animation.finished {
+            continue;
+        }
+        animation.frame_timer.tick(time.delta());
+        for _ in 0..animation.frame_timer.times_finished_this_tick() {
+            sprite.index = animation.next_frame(sprite.index);
+            if animation.finished {
+                break;
+            }
+        }
+    }
+}
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(animate_sprites);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::headless_app;
+
+    #[test]
+    fn one_shot_animations_stop_on_the_last_frame_and_loops_wrap() {
+        let mut app = headless_app();
+        let once = app
+            .world
+            .spawn((
+                TextureAtlasSprite::new(0),
+                SpriteAnimation::new(0, 3, 0.1, AnimationMode::Once),
+            ))
+            .id();
+        let looping = app
+            .world
+            .spawn((
+                TextureAtlasSprite::new(2),
+                SpriteAnimation::new(2, 4, 0.1, AnimationMode::Loop),
+            ))
+            .id();
+
+        // 0.25 seconds is two and a half frames.
+        for _ in 0..15 {
+            app.update();
+        }
+        assert_eq!(app.world.get::<TextureAtlasSprite>(once).unwrap().index, 2);
+        assert!(!app
+            .world
+            .get::<SpriteAnimation>(once)
+            .unwrap()
+            .is_finished());
+        assert_eq!(
+            app.world.get::<TextureAtlasSprite>(looping).unwrap().index,
+            4
+        );
+
+        for _ in 0..18 {
+            app.update();
+        }
+        assert_eq!(app.world.get::<TextureAtlasSprite>(once).unwrap().index, 3);
+        assert!(app
+            .world
+            .get::<SpriteAnimation>(once)
+            .unwrap()
+            .is_finished());
+        assert_eq!(
+            app.world.get::<TextureAtlasSprite>(looping).unwrap().index,
+            4
+        );
+    }
+}
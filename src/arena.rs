@@ -1,24 +1,93 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::CLEAR_COLOR;
+use crate::{settings::Settings, CLEAR_COLOR};
 
+/// Size of the default square arena.
 pub const ARENA_WIDTH: f32 = 900.0;
 pub const ARENA_HEIGHT: f32 = 900.0;
+pub const WIDE_ARENA_WIDTH: f32 = 1600.0;
 
 pub const WALL_COLOR: Color = Color::rgb(0.8, 0.8, 0.8);
 
-pub const LEFT_WALL_X: f32 = 0.0;
-pub const RIGHT_WALL_X: f32 = 900.0;
-
-pub const TOP_WALL_Y: f32 = 900.0;
-pub const BOTTOM_WALL_Y: f32 = 0.0;
-
-pub const HIDE_ARENA_OVERFLOW_AREA_THICKNESS: f32 = 200.0;
+pub const HIDE_ARENA_OVERFLOW_AREA_THICKNESS: f32 = 4.0 * ARENA_WIDTH;
 pub const WALL_THICKNESS: f32 = 10.0;
 
 pub const WALL_LAYER: f32 = 3.0;
 pub const HIDE_OVERFLOW_LAYER: f32 = 2.0;
 
+/// The play area, from the origin to `width` and `height` in world units.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct Arena {
+    pub width: f32,
+    pub height: f32,
+    /// The ship leaves one edge and comes back on the other instead of
+    /// stopping at the walls.
+    pub wrap: bool,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        ArenaLayout::default().arena()
+    }
+}
+
+impl Arena {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.size() / 2.0
+    }
+}
+
+/// Arena shapes to pick from in the settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ArenaLayout {
+    #[default]
+    Square,
+    Widescreen,
+    /// The square arena with wrap-around edges, like the old arcade games.
+    Classic,
+}
+
+impl ArenaLayout {
+    pub const ALL: [ArenaLayout; 3] = [
+        ArenaLayout::Square,
+        ArenaLayout::Widescreen,
+        ArenaLayout::Classic,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArenaLayout::Square => "Square",
+            ArenaLayout::Widescreen => "Widescreen",
+            ArenaLayout::Classic => "Classic",
+        }
+    }
+
+    pub fn arena(&self) -> Arena {
+        match self {
+            ArenaLayout::Square => Arena {
+                width: ARENA_WIDTH,
+                height: ARENA_HEIGHT,
+                wrap: false,
+            },
+            ArenaLayout::Widescreen => Arena {
+                width: WIDE_ARENA_WIDTH,
+                height: ARENA_HEIGHT,
+                wrap: false,
+            },
+            ArenaLayout::Classic => Arena {
+                width: ARENA_WIDTH,
+                height: ARENA_HEIGHT,
+                wrap: true,
+            },
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct ArenaWall {}
 
@@ -35,30 +104,27 @@ enum ArenaWallLocation {
 }
 
 impl ArenaWallLocation {
-    fn position(&self, wall_thickness: f32, draw_layer: f32) -> Vec3 {
+    fn position(&self, arena: &Arena, wall_thickness: f32, draw_layer: f32) -> Vec3 {
+        let center = arena.center();
         match self {
-            ArenaWallLocation::Left => {
-                Vec3::new(LEFT_WALL_X - wall_thickness / 2.0, 450.0, draw_layer)
-            }
+            ArenaWallLocation::Left => Vec3::new(-wall_thickness / 2.0, center.y, draw_layer),
             ArenaWallLocation::Right => {
-                Vec3::new(RIGHT_WALL_X + wall_thickness / 2.0, 450.0, draw_layer)
+                Vec3::new(arena.width + wall_thickness / 2.0, center.y, draw_layer)
             }
             ArenaWallLocation::Top => {
-                Vec3::new(450.0, TOP_WALL_Y + wall_thickness / 2.0, draw_layer)
-            }
-            ArenaWallLocation::Bottom => {
-                Vec3::new(450.0, BOTTOM_WALL_Y - wall_thickness / 2.0, draw_layer)
+                Vec3::new(center.x, arena.height + wall_thickness / 2.0, draw_layer)
             }
+            ArenaWallLocation::Bottom => Vec3::new(center.x, -wall_thickness / 2.0, draw_layer),
         }
     }
 
-    fn size(&self, wall_thickness: f32) -> Vec3 {
+    fn size(&self, arena: &Arena, wall_thickness: f32) -> Vec3 {
         match self {
             ArenaWallLocation::Top | ArenaWallLocation::Bottom => {
-                Vec3::new(ARENA_WIDTH, wall_thickness, 1.0)
+                Vec3::new(arena.width, wall_thickness, 1.0)
             }
             ArenaWallLocation::Left | ArenaWallLocation::Right => {
-                Vec3::new(wall_thickness, ARENA_HEIGHT + wall_thickness * 2.0, 1.0)
+                Vec3::new(wall_thickness, arena.height + wall_thickness * 2.0, 1.0)
             }
         }
     }
@@ -66,6 +132,7 @@ impl ArenaWallLocation {
 
 impl ArenaWallBundle {
     fn new(
+        arena: &Arena,
         location: ArenaWallLocation,
         wall_thickness: f32,
         wall_color: Color,
@@ -74,8 +141,8 @@ impl ArenaWallBundle {
         ArenaWallBundle {
             sprite_bundle: SpriteBundle {
                 transform: Transform {
-                    translation: location.position(wall_thickness, draw_layer),
-                    scale: location.size(wall_thickness),
+                    translation: location.position(arena, wall_thickness, draw_layer),
+                    scale: location.size(arena, wall_thickness),
                     ..default()
                 },
                 sprite: Sprite {
@@ -88,85 +155,51 @@ impl ArenaWallBundle {
     }
 }
 
-pub fn spawn_arena_walls(mut commands: Commands) {
-    commands.spawn((
-        ArenaWallBundle::new(
+/// Rebuilds the walls whenever the arena changes shape. Wrap-around arenas
+/// only keep the overflow cover, since their edges are open.
+pub fn spawn_arena_walls(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    wall_query: Query<Entity, With<ArenaWall>>,
+) {
+    for entity in wall_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let mut walls = vec![(
+        HIDE_ARENA_OVERFLOW_AREA_THICKNESS,
+        CLEAR_COLOR,
+        HIDE_OVERFLOW_LAYER,
+    )];
+    if !arena.wrap {
+        walls.push((WALL_THICKNESS, WALL_COLOR, WALL_LAYER));
+    }
+    for (thickness, color, layer) in walls {
+        for location in [
             ArenaWallLocation::Bottom,
-            HIDE_ARENA_OVERFLOW_AREA_THICKNESS,
-            CLEAR_COLOR,
-            HIDE_OVERFLOW_LAYER,
-        ),
-        ArenaWall {},
-    ));
-    commands.spawn((
-        ArenaWallBundle::new(
             ArenaWallLocation::Top,
-            HIDE_ARENA_OVERFLOW_AREA_THICKNESS,
-            CLEAR_COLOR,
-            HIDE_OVERFLOW_LAYER,
-        ),
-        ArenaWall {},
-    ));
-    commands.spawn((
-        ArenaWallBundle::new(
             ArenaWallLocation::Left,
-            HIDE_ARENA_OVERFLOW_AREA_THICKNESS,
-            CLEAR_COLOR,
-            HIDE_OVERFLOW_LAYER,
-        ),
-        ArenaWall {},
-    ));
-    commands.spawn((
-        ArenaWallBundle::new(
             ArenaWallLocation::Right,
-            HIDE_ARENA_OVERFLOW_AREA_THICKNESS,
-            CLEAR_COLOR,
-            HIDE_OVERFLOW_LAYER,
-        ),
-        ArenaWall {},
-    ));
-    commands.spawn((
-        ArenaWallBundle::new(
-            ArenaWallLocation::Top,
-            WALL_THICKNESS,
-            WALL_COLOR,
-            WALL_LAYER,
-        ),
-        ArenaWall {},
-    ));
-    commands.spawn((
-        ArenaWallBundle::new(
-            ArenaWallLocation::Bottom,
-            WALL_THICKNESS,
-            WALL_COLOR,
-            WALL_LAYER,
-        ),
-        ArenaWall {},
-    ));
-    commands.spawn((
-        ArenaWallBundle::new(
-            ArenaWallLocation::Right,
-            WALL_THICKNESS,
-            WALL_COLOR,
-            WALL_LAYER,
-        ),
-        ArenaWall {},
-    ));
-    commands.spawn((
-        ArenaWallBundle::new(
-            ArenaWallLocation::Left,
-            WALL_THICKNESS,
-            WALL_COLOR,
-            WALL_LAYER,
-        ),
-        ArenaWall {},
-    ));
+        ] {
+            commands.spawn((
+                ArenaWallBundle::new(&arena, location, thickness, color, layer),
+                ArenaWall {},
+            ));
+        }
+    }
+}
+
+/// Settings can only change from the menus, so a run never sees its arena change.
+pub fn apply_arena_layout(settings: Res<Settings>, mut arena: ResMut<Arena>) {
+    let layout_arena = settings.arena.arena();
+    if *arena != layout_arena {
+        *arena = layout_arena;
+    }
 }
 
 pub struct ArenaPlugin;
 
 impl Plugin for ArenaPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_arena_walls);
+        app.add_system(spawn_arena_walls.run_if(resource_changed::<Arena>()));
     }
 }
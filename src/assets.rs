@@ -1,6 +1,11 @@
 use bevy::{prelude::*, render::texture::ImageSampler};
 
-#[derive(Debug, Resource)]
+use crate::{explosion::EXPLOSION_FRAMES, minerals::MINERAL_FRAMES};
+
+/// Number of segments in the effect countdown ring images.
+pub const EFFECT_RING_STEPS: usize = 8;
+
+#[derive(Debug, Resource, Default)]
 pub struct SpriteAssets {
     pub player: Handle<Image>,
     pub big_rock: Handle<Image>,
@@ -9,9 +14,20 @@ pub struct SpriteAssets {
     pub star: Handle<Image>,
     pub health_crate: Handle<Image>,
     pub explosive_crate: Handle<Image>,
+    pub shield_crate: Handle<Image>,
+    pub rapid_fire_crate: Handle<Image>,
+    pub score_crate: Handle<Image>,
+    pub magnet_crate: Handle<Image>,
+    pub slow_motion_crate: Handle<Image>,
+    pub bomb_crate: Handle<Image>,
+    pub double_damage_crate: Handle<Image>,
+    pub speed_boost_crate: Handle<Image>,
+    pub double_score_crate: Handle<Image>,
     pub bullet: Handle<Image>,
-    pub background: Handle<Image>,
-    pub explosion: Handle<Image>,
+    pub explosion_sheet: Handle<TextureAtlas>,
+    pub blast_sheet: Handle<TextureAtlas>,
+    pub magnet_ring: Handle<Image>,
+    pub mineral_sheet: Handle<TextureAtlas>,
 }
 
 #[derive(Resource)]
@@ -20,11 +36,27 @@ pub struct AudioAssets {
     pub collect_repair: Handle<AudioSource>,
     pub hit_explosive: Handle<AudioSource>,
     pub shoot: Handle<AudioSource>,
+    pub collect_shield: Handle<AudioSource>,
+    pub collect_rapid_fire: Handle<AudioSource>,
+    pub collect_score: Handle<AudioSource>,
+    pub collect_magnet: Handle<AudioSource>,
+    pub collect_slow_motion: Handle<AudioSource>,
+    pub collect_bomb: Handle<AudioSource>,
+    pub collect_double_damage: Handle<AudioSource>,
+    pub collect_speed_boost: Handle<AudioSource>,
+    pub collect_double_score: Handle<AudioSource>,
+    pub collect_mineral: Handle<AudioSource>,
+    pub music_menu: Handle<AudioSource>,
+    pub music_cruise: Handle<AudioSource>,
+    pub music_pressure: Handle<AudioSource>,
+    pub music_onslaught: Handle<AudioSource>,
 }
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct UiAssets {
     pub menu_font: Handle<Font>,
+    /// Countdown rings drawn around effect icons, from empty to full.
+    pub effect_rings: Vec<Handle<Image>>,
 }
 
 fn fix_blurry_textures(
@@ -32,12 +64,9 @@ fn fix_blurry_textures(
     mut assets: ResMut<Assets<Image>>,
 ) {
     for ev in ev_asset.iter() {
-        match ev {
-            AssetEvent::Created { handle } => {
-                let texture = assets.get_mut(&handle).unwrap();
-                texture.sampler_descriptor = ImageSampler::nearest();
-            }
-            _ => {}
+        if let AssetEvent::Created { handle } = ev {
+            let texture = assets.get_mut(handle).unwrap();
+            texture.sampler_descriptor = ImageSampler::nearest();
         }
     }
 }
@@ -51,7 +80,24 @@ impl Plugin for AssetsPlugin {
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let mut load_sheet = |path: &str, frame_size: f32, frames: usize| {
+        texture_atlases.add(TextureAtlas::from_grid(
+            asset_server.load(path),
+            Vec2::splat(frame_size),
+            frames,
+            1,
+            None,
+            None,
+        ))
+    };
+    let explosion_sheet = load_sheet("sprites/explosion_sheet.png", 16.0, EXPLOSION_FRAMES);
+    let blast_sheet = load_sheet("sprites/blast_sheet.png", 32.0, EXPLOSION_FRAMES);
+    let mineral_sheet = load_sheet("sprites/mineral_sheet.png", 6.0, MINERAL_FRAMES);
     let sprite_assets = SpriteAssets {
         player: asset_server.load("sprites/ship.png"),
         big_rock: asset_server.load("sprites/big_rock.png"),
@@ -60,9 +106,20 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         star: asset_server.load("sprites/star.png"),
         health_crate: asset_server.load("sprites/repair_crate.png"),
         explosive_crate: asset_server.load("sprites/explosive_crate.png"),
+        shield_crate: asset_server.load("sprites/shield_crate.png"),
+        rapid_fire_crate: asset_server.load("sprites/rapid_fire_crate.png"),
+        score_crate: asset_server.load("sprites/score_crate.png"),
+        magnet_crate: asset_server.load("sprites/magnet_crate.png"),
+        slow_motion_crate: asset_server.load("sprites/slow_motion_crate.png"),
+        bomb_crate: asset_server.load("sprites/bomb_crate.png"),
+        double_damage_crate: asset_server.load("sprites/double_damage_crate.png"),
+        speed_boost_crate: asset_server.load("sprites/speed_boost_crate.png"),
+        double_score_crate: asset_server.load("sprites/double_score_crate.png"),
         bullet: asset_server.load("sprites/bullet.png"),
-        background: asset_server.load("sprites/background.png"),
-        explosion: asset_server.load("sprites/explosion.png"),
+        explosion_sheet,
+        blast_sheet,
+        magnet_ring: asset_server.load("sprites/magnet_ring.png"),
+        mineral_sheet,
     };
     commands.insert_resource(sprite_assets);
     let audio_assets = AudioAssets {
@@ -70,10 +127,27 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         collect_repair: asset_server.load("audio/collect_repair.ogg"),
         hit_explosive: asset_server.load("audio/hit_explosive.ogg"),
         shoot: asset_server.load("audio/shoot.ogg"),
+        collect_shield: asset_server.load("audio/collect_shield.wav"),
+        collect_rapid_fire: asset_server.load("audio/collect_rapid_fire.wav"),
+        collect_score: asset_server.load("audio/collect_score.wav"),
+        collect_magnet: asset_server.load("audio/collect_magnet.wav"),
+        collect_slow_motion: asset_server.load("audio/collect_slow_motion.wav"),
+        collect_bomb: asset_server.load("audio/collect_bomb.wav"),
+        collect_double_damage: asset_server.load("audio/collect_double_damage.wav"),
+        collect_speed_boost: asset_server.load("audio/collect_speed_boost.wav"),
+        collect_double_score: asset_server.load("audio/collect_double_score.wav"),
+        collect_mineral: asset_server.load("audio/collect_mineral.wav"),
+        music_menu: asset_server.load("audio/music_menu.wav"),
+        music_cruise: asset_server.load("audio/music_cruise.wav"),
+        music_pressure: asset_server.load("audio/music_pressure.wav"),
+        music_onslaught: asset_server.load("audio/music_onslaught.wav"),
     };
     commands.insert_resource(audio_assets);
     let ui_assets = UiAssets {
         menu_font: asset_server.load("fonts/m5x7.ttf"),
+        effect_rings: (0..=EFFECT_RING_STEPS)
+            .map(|step| asset_server.load(format!("ui/effect_ring_{}.png", step)))
+            .collect(),
     };
     commands.insert_resource(ui_assets);
 }
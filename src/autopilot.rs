@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+
+use crate::{
+    arena::Arena,
+    input::{ActionSet, PlayerActions},
+    player::{Player, PLAYER_SIZE},
+    rock::Rock,
+    space_crates::{CrateType, SpaceCrate, CRATE_SPEED, CRATE_WIDTH},
+    state::AppState,
+    widgets::{MenuInput, MenuWidgetSet},
+};
+
+/// How far ahead, in seconds, the autopilot looks for things about to hit the ship.
+pub const AUTOPILOT_LOOKAHEAD: f32 = 1.5;
+pub const AUTOPILOT_SAFETY_MARGIN: f32 = 16.0;
+/// Flying low gives the most time to react to rocks coming from the top.
+pub const AUTOPILOT_CRUISE_HEIGHT: f32 = 150.0;
+pub const AUTOPILOT_WALL_MARGIN: f32 = 100.0;
+/// How strongly the autopilot steers toward crates other than health and explosives.
+pub const AUTOPILOT_PICKUP_PULL: f32 = 0.5;
+/// The autopilot drops a bomb once this many rocks are about to hit the ship.
+pub const AUTOPILOT_BOMB_THREAT: usize = 3;
+pub const ATTRACT_MODE_DELAY: f32 = 10.0;
+
+#[derive(Resource, Default)]
+pub struct Autopilot {
+    pub enabled: bool,
+    /// The current run is a main menu demo, any key ends it and it never sets a highscore.
+    pub attract_mode: bool,
+}
+
+#[derive(Resource)]
+pub struct AttractModeTimer {
+    pub timer: Timer,
+}
+
+impl Default for AttractModeTimer {
+    fn default() -> Self {
+        AttractModeTimer {
+            timer: Timer::from_seconds(ATTRACT_MODE_DELAY, TimerMode::Once),
+        }
+    }
+}
+
+/// Sideways push away from something falling at `speed` that would hit the
+/// ship within the lookahead window. Closer threats push harder. Threats
+/// dead ahead are dodged toward `open_side`, the side with more room.
+fn avoid(offset: Vec2, speed: f32, clearance: f32, open_side: f32) -> Vec2 {
+    if offset.x.abs() >= clearance || offset.y < -clearance {
+        return Vec2::ZERO;
+    }
+    let time_to_impact = (offset.y - clearance).max(0.0) / speed;
+    if time_to_impact > AUTOPILOT_LOOKAHEAD {
+        return Vec2::ZERO;
+    }
+    let urgency = 1.0 - time_to_impact / AUTOPILOT_LOOKAHEAD;
+    let direction = if offset.x == 0.0 {
+        open_side
+    } else if offset.x < 0.0 {
+        1.0
+    } else {
+        -1.0
+    };
+    Vec2::new(direction * urgency * 3.0, -urgency * 0.5)
+}
+
+pub fn drive_player(
+    player_query: Query<(&Transform, &Player)>,
+    rock_query: Query<(&Transform, &Rock)>,
+    crate_query: Query<(&Transform, &SpaceCrate)>,
+    mut actions: ResMut<PlayerActions>,
+    arena: Res<Arena>,
+) {
+    let Ok((player_transform, player)) = player_query.get_single() else {
+        *actions = PlayerActions::default();
+        return;
+    };
+    let position = player_transform.translation.truncate();
+    let mut steering = Vec2::ZERO;
+    let mut shoot = false;
+    let mut threats = 0;
+    let open_side = if position.x < arena.center().x {
+        1.0
+    } else {
+        -1.0
+    };
+
+    steering.y +=
+        ((AUTOPILOT_CRUISE_HEIGHT - position.y) / AUTOPILOT_CRUISE_HEIGHT).clamp(-1.0, 1.0);
+
+    for (rock_transform, rock) in rock_query.iter() {
+        let offset = rock_transform.translation.truncate() - position;
+        if offset.y > 0.0 && offset.y < arena.height && offset.x.abs() < rock.size() / 2.0 {
+            shoot = true;
+        }
+        let clearance = (PLAYER_SIZE + rock.size()) / 2.0 + AUTOPILOT_SAFETY_MARGIN;
+        let push = avoid(offset, rock.speed(), clearance, open_side);
+        if push != Vec2::ZERO {
+            threats += 1;
+        }
+        steering += push;
+    }
+
+    let missing_health = 1.0 - player.health / player.max_health;
+    for (crate_transform, space_crate) in crate_query.iter() {
+        let offset = crate_transform.translation.truncate() - position;
+        match space_crate.crate_type {
+            CrateType::Health => {
+                steering += offset.normalize_or_zero() * missing_health * 2.0;
+            }
+            CrateType::Explosive => {
+                let clearance = (PLAYER_SIZE + CRATE_WIDTH) / 2.0 + AUTOPILOT_SAFETY_MARGIN;
+                steering += avoid(offset, CRATE_SPEED, clearance, open_side);
+            }
+            _ => {
+                steering += offset.normalize_or_zero() * AUTOPILOT_PICKUP_PULL;
+            }
+        }
+    }
+
+    if position.x < AUTOPILOT_WALL_MARGIN {
+        steering.x += 1.0 - position.x / AUTOPILOT_WALL_MARGIN;
+    } else if position.x > arena.width - AUTOPILOT_WALL_MARGIN {
+        steering.x -= 1.0 - (arena.width - position.x) / AUTOPILOT_WALL_MARGIN;
+    }
+
+    actions.movement = if steering.length() > 0.1 {
+        steering.clamp_length_max(1.0)
+    } else {
+        Vec2::ZERO
+    };
+    actions.shoot = shoot;
+    actions.bomb = threats >= AUTOPILOT_BOMB_THREAT && player.bombs > 0;
+}
+
+fn autopilot_enabled(autopilot: Res<Autopilot>) -> bool {
+    autopilot.enabled
+}
+
+fn attract_mode_active(autopilot: Res<Autopilot>) -> bool {
+    autopilot.attract_mode
+}
+
+pub fn reset_attract_mode(
+    mut autopilot: ResMut<Autopilot>,
+    mut attract_mode_timer: ResMut<AttractModeTimer>,
+) {
+    if autopilot.attract_mode {
+        *autopilot = Autopilot::default();
+    }
+    attract_mode_timer.timer.reset();
+}
+
+pub fn start_attract_mode(
+    keyboard_input: Res<Input<KeyCode>>,
+    menu_input: Res<MenuInput>,
+    mut attract_mode_timer: ResMut<AttractModeTimer>,
+    mut autopilot: ResMut<Autopilot>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    time: Res<Time>,
+) {
+    if keyboard_input.get_just_pressed().next().is_some() || menu_input.any() {
+        attract_mode_timer.timer.reset();
+        return;
+    }
+    if attract_mode_timer.timer.tick(time.delta()).just_finished() {
+        autopilot.enabled = true;
+        autopilot.attract_mode = true;
+        next_app_state.set(AppState::Game);
+    }
+}
+
+pub fn leave_attract_mode(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.get_just_pressed().next().is_some() {
+        next_app_state.set(AppState::MainMenu);
+    }
+}
+
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Autopilot>()
+            .init_resource::<AttractModeTimer>()
+            .add_system(
+                drive_player
+                    .run_if(autopilot_enabled)
+                    .in_set(ActionSet)
+                    .in_set(OnUpdate(AppState::Game)),
+            )
+            .add_system(reset_attract_mode.in_schedule(OnEnter(AppState::MainMenu)))
+            .add_system(
+                start_attract_mode
+                    .after(MenuWidgetSet)
+                    .in_set(OnUpdate(AppState::MainMenu)),
+            )
+            .add_system(
+                leave_attract_mode
+                    .run_if(attract_mode_active)
+                    .in_set(OnUpdate(AppState::Game)),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        headless::{enter_game, headless_app},
+        rock::{RockSize, RockSpeed},
+    };
+
+    #[test]
+    fn dodges_and_shoots_at_a_rock_falling_onto_the_ship() {
+        let mut app = headless_app();
+        app.insert_resource(Autopilot {
+            enabled: true,
+            attract_mode: false,
+        });
+        enter_game(&mut app);
+        let position = app
+            .world
+            .query_filtered::<&Transform, With<Player>>()
+            .single(&app.world)
+            .translation;
+        app.world.spawn((
+            Transform::from_translation(position + Vec3::new(10.0, 150.0, 0.0)),
+            Rock {
+                rock_size: RockSize::Big,
+                rock_speed: RockSpeed::Fast,
+            },
+        ));
+
+        app.update();
+
+        let actions = app.world.resource::<PlayerActions>();
+        assert!(actions.movement.x < 0.0);
+        assert!(actions.shoot);
+    }
+}
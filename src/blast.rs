@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+
+use crate::{
+    camera_shake::Impact,
+    destruction::PendingDestruction,
+    explosion::{ExplosionKind, SpawnExplosion},
+    gun::{bullet_rock_collision, Bullet, BULLET_HEIGHT, BULLET_WIDTH},
+    minerals::DropMinerals,
+    player::{DamagePlayer, Player},
+    pool::EntityPool,
+    rock::{Rock, RocksDestroyed},
+    score::Score,
+    sound::{PlaySound, SoundEffect},
+    space_crates::{CrateType, SpaceCrate, CRATE_HEIGHT, CRATE_WIDTH},
+    spatial_hash::SpatialHash,
+    state::{AppState, GameSet},
+};
+
+pub const BLAST_RADIUS: f32 = 160.0;
+/// Damage to a player standing right on the crate, falling off to nothing at the edge.
+pub const BLAST_PLAYER_DAMAGE: f32 = 40.0;
+/// Camera trauma of a blast right on the player, falling off like the damage.
+pub const BLAST_PLAYER_TRAUMA: f32 = 0.8;
+
+/// An explosive crate going off at `pos`.
+pub struct Detonation {
+    pub pos: Vec2,
+}
+
+pub fn bullet_crate_collision(
+    mut commands: Commands,
+    bullet_query: Query<(Entity, &Transform), With<Bullet>>,
+    crate_query: Query<(Entity, &Transform, &SpaceCrate)>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
+    mut pending_destruction: ResMut<PendingDestruction>,
+    mut detonation_event_writer: EventWriter<Detonation>,
+) {
+    for (crate_entity, crate_transform, space_crate) in crate_query.iter() {
+        if space_crate.crate_type != CrateType::Explosive
+            || pending_destruction.is_pending(crate_entity)
+        {
+            continue;
+        }
+        for (bullet_entity, bullet_transform) in bullet_query.iter() {
+            if pending_destruction.is_pending(bullet_entity) {
+                continue;
+            }
+            if collide(
+                bullet_transform.translation,
+                Vec2::new(BULLET_WIDTH, BULLET_HEIGHT),
+                crate_transform.translation,
+                Vec2::new(CRATE_WIDTH, CRATE_HEIGHT),
+            )
+            .is_some()
+            {
+                pending_destruction.mark(bullet_entity);
+                pending_destruction.mark(crate_entity);
+                bullet_pool.release(&mut commands, bullet_entity);
+                commands.entity(crate_entity).despawn();
+                detonation_event_writer.send(Detonation {
+                    pos: crate_transform.translation.truncate(),
+                });
+                break;
+            }
+        }
+    }
+}
+
+/// Resolves every detonation of this tick, including explosive crates
+/// caught in a blast, which go off in the same tick.
+pub fn resolve_detonations(
+    mut commands: Commands,
+    mut event_reader: EventReader<Detonation>,
+    rock_query: Query<(Entity, &Transform, &Rock)>,
+    crate_query: Query<(Entity, &Transform, &SpaceCrate)>,
+    player_query: Query<&Transform, With<Player>>,
+    spatial_hash: Res<SpatialHash>,
+    mut candidates: Local<Vec<Entity>>,
+    mut rock_pool: ResMut<EntityPool<Rock>>,
+    mut pending_destruction: ResMut<PendingDestruction>,
+    mut score: ResMut<Score>,
+    mut rocks_destroyed: ResMut<RocksDestroyed>,
+    mut explosion_event_writer: EventWriter<SpawnExplosion>,
+    mut sound_event_writer: EventWriter<PlaySound>,
+    mut damage_event_writer: EventWriter<DamagePlayer>,
+    mut mineral_event_writer: EventWriter<DropMinerals>,
+    mut impact_event_writer: EventWriter<Impact>,
+) {
+    let mut blasts: VecDeque<Vec2> = event_reader.iter().map(|event| event.pos).collect();
+    while let Some(pos) = blasts.pop_front() {
+        explosion_event_writer.send(SpawnExplosion {
+            pos: pos.extend(2.0),
+            kind: ExplosionKind::Crate,
+        });
+        sound_event_writer.send(PlaySound {
+            effect: SoundEffect::HitExplosive,
+            pos: Some(pos),
+        });
+
+        spatial_hash.query(pos, BLAST_RADIUS, &mut candidates);
+        for (rock_entity, rock_transform, rock) in rock_query.iter_many(candidates.iter()) {
+            let distance = rock_transform.translation.truncate().distance(pos);
+            if distance <= BLAST_RADIUS + rock.size() / 2.0 && pending_destruction.mark(rock_entity)
+            {
+                rock_pool.release(&mut commands, rock_entity);
+                score.add(25);
+                explosion_event_writer.send(SpawnExplosion {
+                    pos: rock_transform.translation,
+                    kind: rock.explosion(),
+                });
+                mineral_event_writer.send(DropMinerals {
+                    pos: rock_transform.translation,
+                    amount: rock.minerals(),
+                });
+                rocks_destroyed.count += 1;
+            }
+        }
+
+        for (crate_entity, crate_transform, space_crate) in crate_query.iter() {
+            let crate_pos = crate_transform.translation.truncate();
+            if space_crate.crate_type == CrateType::Explosive
+                && crate_pos.distance(pos) <= BLAST_RADIUS
+                && pending_destruction.mark(crate_entity)
+            {
+                commands.entity(crate_entity).despawn();
+                blasts.push_back(crate_pos);
+            }
+        }
+
+        if let Ok(player_transform) = player_query.get_single() {
+            let distance = player_transform.translation.truncate().distance(pos);
+            if distance < BLAST_RADIUS {
+                let falloff = 1.0 - distance / BLAST_RADIUS;
+                damage_event_writer.send(DamagePlayer {
+                    damage: BLAST_PLAYER_DAMAGE * falloff,
+                });
+                impact_event_writer.send(Impact {
+                    trauma: BLAST_PLAYER_TRAUMA * falloff,
+                });
+            }
+        }
+    }
+}
+
+pub struct BlastPlugin;
+
+impl Plugin for BlastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Detonation>().add_systems(
+            (
+                bullet_crate_collision.after(bullet_rock_collision),
+                resolve_detonations.after(bullet_crate_collision),
+            )
+                .in_set(GameSet::Collision)
+                .in_set(OnUpdate(AppState::Game)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        headless::{enter_game, headless_app},
+        rock::{RockSize, RockSpeed},
+    };
+
+    fn spawn_explosive_crate(app: &mut App, position: Vec3) {
+        app.world.spawn((
+            Transform::from_translation(position),
+            SpaceCrate {
+                crate_type: CrateType::Explosive,
+            },
+        ));
+    }
+
+    fn spawn_rock(app: &mut App, position: Vec3) {
+        app.world.spawn((
+            Transform::from_translation(position),
+            Rock {
+                rock_size: RockSize::Small,
+                rock_speed: RockSpeed::Slow,
+            },
+        ));
+    }
+
+    #[test]
+    fn shot_crate_chains_into_nearby_crate_and_clears_rocks_around_both() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        spawn_explosive_crate(&mut app, Vec3::new(200.0, 700.0, 0.0));
+        spawn_explosive_crate(&mut app, Vec3::new(340.0, 700.0, 0.0));
+        spawn_rock(&mut app, Vec3::new(200.0, 800.0, 0.0));
+        spawn_rock(&mut app, Vec3::new(460.0, 700.0, 0.0));
+        spawn_rock(&mut app, Vec3::new(700.0, 700.0, 0.0));
+        app.world
+            .spawn((Transform::from_xyz(200.0, 700.0, 0.0), Bullet {}));
+
+        app.update();
+
+        assert_eq!(app.world.resource::<RocksDestroyed>().count, 2);
+        assert_eq!(
+            app.world
+                .query_filtered::<(), With<SpaceCrate>>()
+                .iter(&app.world)
+                .count(),
+            0
+        );
+        assert_eq!(
+            app.world
+                .query_filtered::<(), With<Rock>>()
+                .iter(&app.world)
+                .count(),
+            1
+        );
+    }
+}
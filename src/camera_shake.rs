@@ -0,0 +1,154 @@
+use bevy::{prelude::*, transform::TransformSystem};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+/// Trauma lost per second. Shake strength is trauma squared, so it dies down quickly.
+pub const TRAUMA_DECAY: f32 = 1.5;
+/// Camera offset in pixels at full trauma.
+pub const MAX_SHAKE_OFFSET: f32 = 24.0;
+/// How fast the camera jitters, in radians of the noise per second.
+pub const SHAKE_FREQUENCY: f32 = 45.0;
+/// Impacts at least this strong freeze the game for a moment.
+pub const HIT_STOP_TRAUMA: f32 = 0.5;
+pub const HIT_STOP_SECONDS: f32 = 0.08;
+
+/// Something hit hard enough to be felt. `trauma` runs from 0 to 1.
+pub struct Impact {
+    pub trauma: f32,
+}
+
+/// Player preferences for impact feedback, part of `Settings`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ScreenShakeSettings {
+    /// Accessibility toggle, turns camera shake off entirely.
+    pub shake: bool,
+    pub hit_stop: bool,
+}
+
+impl Default for ScreenShakeSettings {
+    fn default() -> Self {
+        ScreenShakeSettings {
+            shake: true,
+            hit_stop: true,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct CameraTrauma {
+    pub trauma: f32,
+    /// Offset applied to the camera last frame, taken back before the next one.
+    offset: Vec2,
+    /// Real seconds left of the current hit-stop.
+    hit_stop: f32,
+}
+
+pub fn add_trauma(
+    mut impact_event_reader: EventReader<Impact>,
+    mut camera_trauma: ResMut<CameraTrauma>,
+    settings: Res<Settings>,
+    mut time: ResMut<Time>,
+) {
+    for impact in impact_event_reader.iter() {
+        camera_trauma.trauma = (camera_trauma.trauma + impact.trauma).min(1.0);
+        if settings.screen_shake.hit_stop && impact.trauma >= HIT_STOP_TRAUMA {
+            camera_trauma.hit_stop = HIT_STOP_SECONDS;
+            time.pause();
+        }
+    }
+}
+
+/// Hit-stop pauses game time, so this counts down in real time.
+pub fn tick_hit_stop(mut camera_trauma: ResMut<CameraTrauma>, mut time: ResMut<Time>) {
+    if camera_trauma.hit_stop <= 0.0 {
+        return;
+    }
+    camera_trauma.hit_stop -= time.raw_delta_seconds();
+    if camera_trauma.hit_stop <= 0.0 {
+        time.unpause();
+    }
+}
+
+pub fn shake_camera(
+    mut camera_trauma: ResMut<CameraTrauma>,
+    settings: Res<Settings>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+    time: Res<Time>,
+) {
+    camera_trauma.trauma =
+        (camera_trauma.trauma - TRAUMA_DECAY * time.raw_delta_seconds()).max(0.0);
+    let shake = if settings.screen_shake.shake {
+        camera_trauma.trauma * camera_trauma.trauma
+    } else {
+        0.0
+    };
+    let t = time.raw_elapsed_seconds() * SHAKE_FREQUENCY;
+    // Two sines with unrelated frequencies per axis wobble without repeating visibly.
+    let noise = Vec2::new(
+        (t * 1.0).sin() + (t * 2.3 + 1.7).sin(),
+        (t * 1.3 + 0.5).sin() + (t * 2.9 + 4.1).sin(),
+    ) / 2.0;
+    let offset = noise * MAX_SHAKE_OFFSET * shake;
+    for mut transform in camera_query.iter_mut() {
+        transform.translation += (offset - camera_trauma.offset).extend(0.0);
+    }
+    camera_trauma.offset = offset;
+}
+
+/// Shakes the camera on impacts. `Impact` and `Settings` live in `GamePlugin`.
+pub struct ScreenShakePlugin;
+
+impl Plugin for ScreenShakePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraTrauma>().add_systems(
+            (tick_hit_stop, add_trauma, shake_camera)
+                .chain()
+                .in_base_set(CoreSet::PostUpdate)
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::headless_app;
+
+    fn camera_offset(app: &mut App) -> Vec2 {
+        app.world
+            .query_filtered::<&Transform, With<Camera2d>>()
+            .single(&app.world)
+            .translation
+            .truncate()
+    }
+
+    #[test]
+    fn impacts_shake_the_camera_back_to_rest_and_big_ones_freeze_time() {
+        let mut app = headless_app();
+        app.add_plugin(ScreenShakePlugin);
+        app.world
+            .spawn((Camera2d::default(), Transform::from_xyz(0.0, 0.0, 999.0)));
+        app.update();
+
+        app.world.send_event(Impact { trauma: 0.3 });
+        app.update();
+        assert!(!app.world.resource::<Time>().is_paused());
+        assert_ne!(camera_offset(&mut app), Vec2::ZERO);
+
+        app.world.send_event(Impact { trauma: 1.0 });
+        app.update();
+        assert!(app.world.resource::<Time>().is_paused());
+        for _ in 0..60 {
+            app.update();
+        }
+        assert!(!app.world.resource::<Time>().is_paused());
+        assert!(camera_offset(&mut app).abs_diff_eq(Vec2::ZERO, 0.001));
+
+        app.world.resource_mut::<Settings>().screen_shake.shake = false;
+        app.world.send_event(Impact { trauma: 0.3 });
+        app.update();
+        assert!(camera_offset(&mut app).abs_diff_eq(Vec2::ZERO, 0.001));
+    }
+}
@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+use crate::{assets::UiAssets, explosion::Explosion, gun::Bullet, pool::EntityPool, rock::Rock};
+
+#[derive(Component)]
+struct DebugOverlay;
+
+#[derive(Component)]
+struct PoolStatsText;
+
+fn toggle_debug_overlay(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    ui_assets: Res<UiAssets>,
+    overlay_query: Query<Entity, With<DebugOverlay>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F3) {
+        return;
+    }
+    if let Ok(overlay) = overlay_query.get_single() {
+        commands.entity(overlay).despawn_recursive();
+        return;
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(10.0),
+                        bottom: Val::Px(10.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                ..default()
+            },
+            DebugOverlay {},
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: ui_assets.menu_font.clone(),
+                            font_size: 25.0,
+                            color: Color::rgb(0.6, 1.0, 0.6),
+                        },
+                    ),
+                    ..default()
+                },
+                PoolStatsText {},
+            ));
+        });
+}
+
+fn update_pool_stats_text(
+    mut text_query: Query<&mut Text, With<PoolStatsText>>,
+    bullet_pool: Res<EntityPool<Bullet>>,
+    rock_pool: Res<EntityPool<Rock>>,
+    explosion_pool: Res<EntityPool<Explosion>>,
+) {
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = [
+            ("Bullets", bullet_pool.stats()),
+            ("Rocks", rock_pool.stats()),
+            ("Explosions", explosion_pool.stats()),
+        ]
+        .iter()
+        .map(|(name, stats)| {
+            format!(
+                "{}: {} active, {} pooled, {} spawned, {} reused",
+                name, stats.active, stats.pooled, stats.spawned, stats.reused
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    }
+}
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems((toggle_debug_overlay, update_pool_stats_text));
+    }
+}
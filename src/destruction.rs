@@ -0,0 +1,36 @@
+use bevy::{prelude::*, utils::HashSet};
+
+/// Entities that have already been destroyed this tick.
+///
+/// Despawns and pool releases are deferred commands, so until the end of the
+/// frame a destroyed rock or bullet is still visible to every query. Systems
+/// claim an entity with [`PendingDestruction::mark`] before scoring or
+/// releasing it, which makes sure each one is only resolved once.
+#[derive(Resource, Default)]
+pub struct PendingDestruction {
+    entities: HashSet<Entity>,
+}
+
+impl PendingDestruction {
+    /// Returns `false` if the entity was already claimed this tick.
+    pub fn mark(&mut self, entity: Entity) -> bool {
+        self.entities.insert(entity)
+    }
+
+    pub fn is_pending(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+}
+
+fn clear_pending_destruction(mut pending_destruction: ResMut<PendingDestruction>) {
+    pending_destruction.entities.clear();
+}
+
+pub struct DestructionPlugin;
+
+impl Plugin for DestructionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingDestruction>()
+            .add_system(clear_pending_destruction.in_base_set(CoreSet::Last));
+    }
+}
@@ -0,0 +1,290 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    assets::SpriteAssets,
+    rock::tick_rock_spawn_timer,
+    score::Score,
+    space_crates::tick_crate_spawn_timer,
+    state::{AppState, GameSet},
+};
+
+pub const RAPID_FIRE_DURATION: f32 = 8.0;
+pub const MAGNET_DURATION: f32 = 10.0;
+pub const SLOW_MOTION_DURATION: f32 = 6.0;
+pub const DOUBLE_DAMAGE_DURATION: f32 = 8.0;
+pub const SPEED_BOOST_DURATION: f32 = 8.0;
+pub const DOUBLE_SCORE_DURATION: f32 = 12.0;
+/// How fast rocks and crates move while slow motion is active.
+pub const SLOW_MOTION_FACTOR: f32 = 0.5;
+pub const SPEED_BOOST_FACTOR: f32 = 1.5;
+
+/// Every effect that can be active on the player for a limited time.
+/// The order here is the order of the icons in the HUD.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EffectKind {
+    RapidFire,
+    DoubleDamage,
+    SpeedBoost,
+    DoubleScore,
+    Magnet,
+    SlowMotion,
+}
+
+/// What happens when an effect is applied while it is still active.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stacking {
+    /// Start the full duration again.
+    Refresh,
+    /// Add the full duration to what is left, up to `max` seconds.
+    Extend { max: f32 },
+}
+
+impl EffectKind {
+    pub const ALL: [EffectKind; 6] = [
+        EffectKind::RapidFire,
+        EffectKind::DoubleDamage,
+        EffectKind::SpeedBoost,
+        EffectKind::DoubleScore,
+        EffectKind::Magnet,
+        EffectKind::SlowMotion,
+    ];
+
+    pub fn duration(&self) -> f32 {
+        match self {
+            EffectKind::RapidFire => RAPID_FIRE_DURATION,
+            EffectKind::DoubleDamage => DOUBLE_DAMAGE_DURATION,
+            EffectKind::SpeedBoost => SPEED_BOOST_DURATION,
+            EffectKind::DoubleScore => DOUBLE_SCORE_DURATION,
+            EffectKind::Magnet => MAGNET_DURATION,
+            EffectKind::SlowMotion => SLOW_MOTION_DURATION,
+        }
+    }
+
+    pub fn stacking(&self) -> Stacking {
+        match self {
+            EffectKind::DoubleScore | EffectKind::Magnet => Stacking::Extend {
+                max: 3.0 * self.duration(),
+            },
+            _ => Stacking::Refresh,
+        }
+    }
+
+    pub fn icon(&self, handles: &SpriteAssets) -> Handle<Image> {
+        match self {
+            EffectKind::RapidFire => handles.rapid_fire_crate.clone(),
+            EffectKind::DoubleDamage => handles.double_damage_crate.clone(),
+            EffectKind::SpeedBoost => handles.speed_boost_crate.clone(),
+            EffectKind::DoubleScore => handles.double_score_crate.clone(),
+            EffectKind::Magnet => handles.magnet_crate.clone(),
+            EffectKind::SlowMotion => handles.slow_motion_crate.clone(),
+        }
+    }
+}
+
+pub struct ActiveEffect {
+    pub kind: EffectKind,
+    pub timer: Timer,
+}
+
+/// Effects currently running on the player. Systems ask this instead of
+/// keeping their own timers.
+#[derive(Resource, Default)]
+pub struct ActiveEffects {
+    effects: Vec<ActiveEffect>,
+}
+
+impl ActiveEffects {
+    pub fn apply(&mut self, kind: EffectKind) {
+        let duration = kind.duration();
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => match kind.stacking() {
+                Stacking::Refresh => effect.timer.reset(),
+                Stacking::Extend { max } => {
+                    let remaining = effect.timer.remaining_secs();
+                    effect
+                        .timer
+                        .set_duration(Duration::from_secs_f32((remaining + duration).min(max)));
+                    effect.timer.reset();
+                }
+            },
+            None => self.effects.push(ActiveEffect {
+                kind,
+                timer: Timer::from_seconds(duration, TimerMode::Once),
+            }),
+        }
+    }
+
+    pub fn is_active(&self, kind: EffectKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// How much of the effect is left, from 1.0 when just applied down to 0.0.
+    pub fn remaining_fraction(&self, kind: EffectKind) -> Option<f32> {
+        self.effects
+            .iter()
+            .find(|effect| effect.kind == kind)
+            .map(|effect| effect.timer.percent_left())
+    }
+
+    /// Multiplier for how fast the world moves around the player.
+    pub fn world_speed(&self) -> f32 {
+        if self.is_active(EffectKind::SlowMotion) {
+            SLOW_MOTION_FACTOR
+        } else {
+            1.0
+        }
+    }
+
+    pub fn player_speed(&self) -> f32 {
+        if self.is_active(EffectKind::SpeedBoost) {
+            SPEED_BOOST_FACTOR
+        } else {
+            1.0
+        }
+    }
+
+    /// How many rocks a single bullet can break.
+    pub fn bullet_damage(&self) -> u32 {
+        if self.is_active(EffectKind::DoubleDamage) {
+            2
+        } else {
+            1
+        }
+    }
+
+    pub fn score_multiplier(&self) -> u32 {
+        if self.is_active(EffectKind::DoubleScore) {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+pub struct ApplyEffect {
+    pub kind: EffectKind,
+}
+
+pub struct EffectExpired {
+    pub kind: EffectKind,
+}
+
+pub fn tick_active_effects(
+    mut active_effects: ResMut<ActiveEffects>,
+    mut expired_event_writer: EventWriter<EffectExpired>,
+    time: Res<Time>,
+) {
+    for effect in active_effects.effects.iter_mut() {
+        effect.timer.tick(time.delta());
+    }
+    active_effects.effects.retain(|effect| {
+        if effect.timer.finished() {
+            expired_event_writer.send(EffectExpired { kind: effect.kind });
+        }
+        !effect.timer.finished()
+    });
+}
+
+pub fn apply_effects(
+    mut event_reader: EventReader<ApplyEffect>,
+    mut active_effects: ResMut<ActiveEffects>,
+) {
+    for event in event_reader.iter() {
+        active_effects.apply(event.kind);
+    }
+}
+
+pub fn update_score_multiplier(active_effects: Res<ActiveEffects>, mut score: ResMut<Score>) {
+    let multiplier = active_effects.score_multiplier();
+    if score.multiplier != multiplier {
+        score.multiplier = multiplier;
+    }
+}
+
+pub fn add_active_effects_resource(mut commands: Commands) {
+    commands.insert_resource(ActiveEffects::default())
+}
+
+pub fn remove_active_effects_resource(mut commands: Commands) {
+    commands.remove_resource::<ActiveEffects>();
+}
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyEffect>()
+            .add_event::<EffectExpired>()
+            .add_system(add_active_effects_resource.in_schedule(OnEnter(AppState::Game)))
+            .add_systems(
+                (
+                    tick_active_effects
+                        .before(tick_rock_spawn_timer)
+                        .before(tick_crate_spawn_timer)
+                        .in_set(GameSet::Timers),
+                    apply_effects.in_set(GameSet::Effects),
+                    update_score_multiplier
+                        .after(apply_effects)
+                        .in_set(GameSet::Effects),
+                )
+                    .in_set(OnUpdate(AppState::Game)),
+            )
+            .add_system(remove_active_effects_resource.in_schedule(OnExit(AppState::Game)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{enter_game, headless_app};
+
+    #[test]
+    fn refreshing_effects_restart_and_extending_effects_are_capped() {
+        let mut active_effects = ActiveEffects::default();
+        active_effects.apply(EffectKind::RapidFire);
+        active_effects.apply(EffectKind::DoubleScore);
+        for effect in active_effects.effects.iter_mut() {
+            effect.timer.tick(Duration::from_secs_f32(4.0));
+        }
+
+        active_effects.apply(EffectKind::RapidFire);
+        active_effects.apply(EffectKind::DoubleScore);
+        active_effects.apply(EffectKind::DoubleScore);
+        active_effects.apply(EffectKind::DoubleScore);
+
+        assert_eq!(active_effects.effects.len(), 2);
+        assert_eq!(
+            active_effects.remaining_fraction(EffectKind::RapidFire),
+            Some(1.0)
+        );
+        let double_score = &active_effects.effects[1].timer;
+        assert_eq!(
+            double_score.duration().as_secs_f32(),
+            3.0 * DOUBLE_SCORE_DURATION
+        );
+    }
+
+    #[test]
+    fn score_multiplier_holds_when_an_effect_is_picked_up_as_it_runs_out() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        let mut active_effects = app.world.resource_mut::<ActiveEffects>();
+        active_effects.apply(EffectKind::DoubleScore);
+        let timer = &mut active_effects.effects[0].timer;
+        let duration = timer.duration();
+        timer.set_elapsed(duration);
+        app.world.send_event(ApplyEffect {
+            kind: EffectKind::DoubleScore,
+        });
+
+        app.update();
+
+        assert!(app
+            .world
+            .resource::<ActiveEffects>()
+            .is_active(EffectKind::DoubleScore));
+        assert_eq!(app.world.resource::<Score>().multiplier, 2);
+    }
+}
@@ -1,56 +1,103 @@
 use bevy::prelude::*;
 
-use crate::{assets::SpriteAssets, ASSET_SCALE};
+use crate::{
+    animation::{animate_sprites, AnimationMode, SpriteAnimation},
+    assets::SpriteAssets,
+    pool::EntityPool,
+    state::GameSet,
+    ASSET_SCALE,
+};
 
-pub const EXPLOSION_LENGTH: f32 = 0.2;
+pub const EXPLOSION_FRAMES: usize = 8;
+/// Frame length of most explosions, the whole animation lasts about 0.25 s.
+pub const EXPLOSION_FRAME_SECONDS: f32 = 0.03;
 
-pub struct SpawnExplosion {
-    pub pos: Vec3,
+/// What blew up, which decides the size and look of the explosion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExplosionKind {
+    SmallRock,
+    Rock,
+    BigRock,
+    Crate,
+    Player,
 }
 
-#[derive(Component)]
-pub struct Explosion {
-    pub timer: Timer,
-}
+impl ExplosionKind {
+    fn sheet(&self, handles: &SpriteAssets) -> Handle<TextureAtlas> {
+        match self {
+            ExplosionKind::SmallRock | ExplosionKind::Rock | ExplosionKind::BigRock => {
+                handles.explosion_sheet.clone()
+            }
+            ExplosionKind::Crate | ExplosionKind::Player => handles.blast_sheet.clone(),
+        }
+    }
+
+    fn scale(&self) -> f32 {
+        match self {
+            ExplosionKind::SmallRock => 0.75 * ASSET_SCALE,
+            ExplosionKind::Rock => ASSET_SCALE,
+            ExplosionKind::BigRock => 1.75 * ASSET_SCALE,
+            ExplosionKind::Crate => 2.5 * ASSET_SCALE,
+            ExplosionKind::Player => 3.0 * ASSET_SCALE,
+        }
+    }
 
-impl Default for Explosion {
-    fn default() -> Self {
-        return Explosion {
-            timer: Timer::from_seconds(EXPLOSION_LENGTH, TimerMode::Once),
-        };
+    fn frame_seconds(&self) -> f32 {
+        match self {
+            ExplosionKind::Crate => 1.5 * EXPLOSION_FRAME_SECONDS,
+            ExplosionKind::Player => 3.0 * EXPLOSION_FRAME_SECONDS,
+            _ => EXPLOSION_FRAME_SECONDS,
+        }
     }
 }
 
+pub struct SpawnExplosion {
+    pub pos: Vec3,
+    pub kind: ExplosionKind,
+}
+
+#[derive(Component)]
+pub struct Explosion;
+
 fn spawn_explosion_on_event(
     mut commands: Commands,
     mut explosion_event_reader: EventReader<SpawnExplosion>,
     handles: Res<SpriteAssets>,
+    mut explosion_pool: ResMut<EntityPool<Explosion>>,
 ) {
     for e in explosion_event_reader.iter() {
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform {
-                    translation: e.pos,
-                    scale: Vec3::new(ASSET_SCALE, ASSET_SCALE, 1.0),
+        explosion_pool.acquire(
+            &mut commands,
+            (
+                SpriteSheetBundle {
+                    transform: Transform {
+                        translation: e.pos,
+                        scale: Vec3::new(e.kind.scale(), e.kind.scale(), 1.0),
+                        ..default()
+                    },
+                    texture_atlas: e.kind.sheet(&handles),
                     ..default()
                 },
-                texture: handles.explosion.clone(),
-                ..default()
-            },
-            Explosion::default(),
-        ));
+                SpriteAnimation::new(
+                    0,
+                    EXPLOSION_FRAMES - 1,
+                    e.kind.frame_seconds(),
+                    AnimationMode::Once,
+                ),
+                Explosion {},
+            ),
+        );
     }
 }
 
-fn tick_explosion_timers(
+fn release_finished_explosions(
     mut commands: Commands,
-    mut explosion_query: Query<(Entity, &mut Explosion), With<Explosion>>,
-    time: Res<Time>,
+    explosion_query: Query<(Entity, &SpriteAnimation), With<Explosion>>,
+    mut explosion_pool: ResMut<EntityPool<Explosion>>,
 ) {
-    for (entity, mut e) in explosion_query.iter_mut() {
-        e.timer.tick(time.delta());
-        if e.timer.finished() {
-            commands.entity(entity).despawn();
+    for (entity, animation) in explosion_query.iter() {
+        if animation.is_finished() {
+            explosion_pool.release(&mut commands, entity);
         }
     }
 }
@@ -60,6 +107,13 @@ pub struct ExplosionPlugin;
 impl Plugin for ExplosionPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<SpawnExplosion>()
-            .add_systems((spawn_explosion_on_event, tick_explosion_timers));
+            .init_resource::<EntityPool<Explosion>>()
+            .add_systems(
+                (
+                    release_finished_explosions.after(animate_sprites),
+                    spawn_explosion_on_event.after(release_finished_explosions),
+                )
+                    .after(GameSet::Effects),
+            );
     }
 }
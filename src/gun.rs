@@ -3,13 +3,23 @@ use std::time::Duration;
 use bevy::{prelude::*, sprite::collide_aabb::collide};
 
 use crate::{
-    arena::ARENA_HEIGHT,
-    assets::{AudioAssets, SpriteAssets},
+    arena::Arena,
+    assets::SpriteAssets,
+    blast::{bullet_crate_collision, resolve_detonations},
+    destruction::PendingDestruction,
+    effects::{ActiveEffects, EffectKind},
     explosion::SpawnExplosion,
-    player::Player,
-    rock::{Rock, RocksDestroyed},
+    input::PlayerActions,
+    minerals::DropMinerals,
+    player::{Player, UpdatePlayerHealth},
+    pool::EntityPool,
+    rock::{update_rock_spatial_hash, Rock, RocksDestroyed},
+    save::SaveData,
     score::Score,
-    state::AppState,
+    sound::{PlaySound, SoundEffect},
+    space_crates::spawn_crates,
+    spatial_hash::SpatialHash,
+    state::{AppState, GameSet},
     ASSET_SCALE,
 };
 
@@ -17,61 +27,176 @@ pub const BULLET_SPEED: f32 = 600.0;
 pub const BULLET_WIDTH: f32 = ASSET_SCALE;
 pub const BULLET_HEIGHT: f32 = 4.0 * ASSET_SCALE;
 pub const BULLET_COOLDOWN: f32 = 1.0;
+pub const RAPID_FIRE_COOLDOWN: f32 = 0.2;
+/// Distance between the bullets of a shot that fires more than one.
+pub const BULLET_SPREAD: f32 = 8.0 * ASSET_SCALE;
 
 #[derive(Component)]
 pub struct Bullet;
 
+/// Sent where a bullet breaks a rock.
+pub struct BulletHit {
+    pub pos: Vec2,
+}
+
+/// How many more rocks a bullet can break. Bullets without it break one.
+#[derive(Component)]
+pub struct BulletDamage {
+    pub remaining: u32,
+}
+
+/// What the gun fires this run, improved by in-run upgrades.
+#[derive(Resource)]
+pub struct GunStats {
+    /// Bullets fired side by side with every shot.
+    pub bullets: u32,
+    /// Extra rocks each bullet breaks before it is used up.
+    pub piercing: u32,
+}
+
+impl Default for GunStats {
+    fn default() -> Self {
+        GunStats {
+            bullets: 1,
+            piercing: 0,
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct BulletCooldownTimer {
     timer: Timer,
+    /// Seconds between shots without rapid fire.
+    pub cooldown: f32,
 }
 
-impl Default for BulletCooldownTimer {
-    fn default() -> Self {
+impl BulletCooldownTimer {
+    pub fn new(cooldown: f32) -> Self {
         let mut bullet_cooldown_timer = BulletCooldownTimer {
-            timer: Timer::from_seconds(BULLET_COOLDOWN, TimerMode::Once),
+            timer: Timer::from_seconds(cooldown, TimerMode::Once),
+            cooldown,
         };
 
         bullet_cooldown_timer
             .timer
-            .set_elapsed(Duration::from_secs_f32(BULLET_COOLDOWN));
+            .set_elapsed(Duration::from_secs_f32(cooldown));
+
+        bullet_cooldown_timer
+    }
+}
 
-        return bullet_cooldown_timer;
+impl Default for BulletCooldownTimer {
+    fn default() -> Self {
+        BulletCooldownTimer::new(BULLET_COOLDOWN)
     }
 }
 
 pub fn shoot(
     mut commands: Commands,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<PlayerActions>,
     sprite_handles: Res<SpriteAssets>,
     player_query: Query<&Transform, With<Player>>,
     mut bullet_cooldown_timer: ResMut<BulletCooldownTimer>,
-    audio: Res<Audio>,
-    audio_handles: Res<AudioAssets>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
+    active_effects: Res<ActiveEffects>,
+    gun_stats: Res<GunStats>,
+    mut sound_event_writer: EventWriter<PlaySound>,
 ) {
     if let Ok(transform) = player_query.get_single() {
-        if keyboard_input.pressed(KeyCode::Space) && bullet_cooldown_timer.timer.finished() {
-            let transform =
-                Transform::from_xyz(transform.translation.x, transform.translation.y, -1.0)
-                    .with_scale(Vec3 {
-                        x: ASSET_SCALE,
-                        y: ASSET_SCALE,
-                        ..default()
-                    });
-            commands.spawn((
-                SpriteBundle {
-                    transform,
-                    texture: sprite_handles.bullet.clone(),
+        if actions.shoot && bullet_cooldown_timer.timer.finished() {
+            for index in 0..gun_stats.bullets {
+                let offset = (index as f32 - (gun_stats.bullets - 1) as f32 / 2.0) * BULLET_SPREAD;
+                let transform = Transform::from_xyz(
+                    transform.translation.x + offset,
+                    transform.translation.y,
+                    -1.0,
+                )
+                .with_scale(Vec3 {
+                    x: ASSET_SCALE,
+                    y: ASSET_SCALE,
                     ..default()
-                },
-                Bullet {},
-            ));
-            audio.play(audio_handles.shoot.clone());
+                });
+                bullet_pool.acquire(
+                    &mut commands,
+                    (
+                        SpriteBundle {
+                            transform,
+                            texture: sprite_handles.bullet.clone(),
+                            ..default()
+                        },
+                        Bullet {},
+                        BulletDamage {
+                            remaining: active_effects.bullet_damage() + gun_stats.piercing,
+                        },
+                    ),
+                );
+            }
+            sound_event_writer.send(PlaySound {
+                effect: SoundEffect::Shoot,
+                pos: Some(transform.translation.truncate()),
+            });
+            let cooldown = if active_effects.is_active(EffectKind::RapidFire) {
+                RAPID_FIRE_COOLDOWN.min(bullet_cooldown_timer.cooldown)
+            } else {
+                bullet_cooldown_timer.cooldown
+            };
+            bullet_cooldown_timer
+                .timer
+                .set_duration(Duration::from_secs_f32(cooldown));
             bullet_cooldown_timer.timer.reset();
         }
     }
 }
 
+/// Clears every rock that has entered the arena.
+pub fn detonate_bomb(
+    mut commands: Commands,
+    actions: Res<PlayerActions>,
+    mut player_query: Query<&mut Player>,
+    rock_query: Query<(Entity, &Transform, &Rock)>,
+    mut rock_pool: ResMut<EntityPool<Rock>>,
+    mut pending_destruction: ResMut<PendingDestruction>,
+    mut score: ResMut<Score>,
+    mut rocks_destroyed: ResMut<RocksDestroyed>,
+    mut explosion_event_writer: EventWriter<SpawnExplosion>,
+    mut sound_event_writer: EventWriter<PlaySound>,
+    mut update_health_event_writer: EventWriter<UpdatePlayerHealth>,
+    mut mineral_event_writer: EventWriter<DropMinerals>,
+    arena: Res<Arena>,
+) {
+    if !actions.bomb {
+        return;
+    }
+    let Ok(mut player) = player_query.get_single_mut() else {
+        return;
+    };
+    if player.bombs == 0 {
+        return;
+    }
+    player.bombs -= 1;
+    update_health_event_writer.send(UpdatePlayerHealth {});
+    sound_event_writer.send(PlaySound {
+        effect: SoundEffect::HitExplosive,
+        pos: None,
+    });
+
+    for (rock_entity, rock_transform, rock) in rock_query.iter() {
+        if rock_transform.translation.y <= arena.height && pending_destruction.mark(rock_entity) {
+            rock_pool.release(&mut commands, rock_entity);
+            score.add(25);
+            explosion_event_writer.send(SpawnExplosion {
+                pos: rock_transform.translation,
+                kind: rock.explosion(),
+            });
+            mineral_event_writer.send(DropMinerals {
+                pos: rock_transform.translation,
+                amount: rock.minerals(),
+            });
+            rocks_destroyed.count += 1;
+        }
+    }
+}
+
 pub fn move_bullets(mut bullet_query: Query<&mut Transform, With<Bullet>>, time: Res<Time>) {
     for mut transform in bullet_query.iter_mut() {
         transform.translation.y += BULLET_SPEED * time.delta_seconds();
@@ -81,22 +206,41 @@ pub fn move_bullets(mut bullet_query: Query<&mut Transform, With<Bullet>>, time:
 pub fn despawn_off_screen_bullets(
     mut commands: Commands,
     bullet_query: Query<(Entity, &Transform), With<Bullet>>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
+    mut pending_destruction: ResMut<PendingDestruction>,
+    arena: Res<Arena>,
 ) {
     for (bullet_entity, bullet_transform) in bullet_query.iter() {
-        if bullet_transform.translation.y > ARENA_HEIGHT + BULLET_HEIGHT {
-            commands.entity(bullet_entity).despawn();
+        if bullet_transform.translation.y > arena.height + BULLET_HEIGHT
+            && pending_destruction.mark(bullet_entity)
+        {
+            bullet_pool.release(&mut commands, bullet_entity);
         }
     }
 }
 
-pub fn despawn_all_bullets(mut commands: Commands, bullet_query: Query<Entity, With<Bullet>>) {
+pub fn despawn_all_bullets(
+    mut commands: Commands,
+    bullet_query: Query<Entity, With<Bullet>>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
+) {
     for entity in bullet_query.iter() {
-        commands.entity(entity).despawn();
+        bullet_pool.release(&mut commands, entity);
     }
 }
 
-pub fn add_bullet_cooldown_timer_resource(mut commands: Commands) {
-    commands.insert_resource(BulletCooldownTimer::default())
+pub fn add_bullet_cooldown_timer_resource(mut commands: Commands, save_data: Res<SaveData>) {
+    commands.insert_resource(BulletCooldownTimer::new(
+        save_data.upgrades.bullet_cooldown(),
+    ))
+}
+
+pub fn add_gun_stats_resource(mut commands: Commands) {
+    commands.insert_resource(GunStats::default())
+}
+
+pub fn remove_gun_stats_resource(mut commands: Commands) {
+    commands.remove_resource::<GunStats>();
 }
 
 pub fn remove_bullet_cooldown_timer_resource(mut commands: Commands) {
@@ -112,16 +256,34 @@ pub fn tick_bullet_cooldown_timer(
 
 pub fn bullet_rock_collision(
     mut commands: Commands,
-    bullet_query: Query<(Entity, &Transform), With<Bullet>>,
+    mut bullet_query: Query<(Entity, &Transform, Option<&mut BulletDamage>), With<Bullet>>,
     rock_query: Query<(Entity, &Transform, &Rock), With<Rock>>,
+    spatial_hash: Res<SpatialHash>,
+    mut candidates: Local<Vec<Entity>>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
+    mut rock_pool: ResMut<EntityPool<Rock>>,
+    mut pending_destruction: ResMut<PendingDestruction>,
     mut score: ResMut<Score>,
-    audio: Res<Audio>,
-    audio_handles: Res<AudioAssets>,
+    mut sound_event_writer: EventWriter<PlaySound>,
     mut rocks_destroyed: ResMut<RocksDestroyed>,
     mut explosion_event_writer: EventWriter<SpawnExplosion>,
+    mut mineral_event_writer: EventWriter<DropMinerals>,
+    mut bullet_hit_event_writer: EventWriter<BulletHit>,
 ) {
-    for (bullet_entity, bullet_transform) in bullet_query.iter() {
-        for (rock_entity, rock_transform, rock) in rock_query.iter() {
+    for (bullet_entity, bullet_transform, mut bullet_damage) in bullet_query.iter_mut() {
+        if pending_destruction.is_pending(bullet_entity) {
+            continue;
+        }
+        let mut remaining = bullet_damage.as_ref().map_or(1, |damage| damage.remaining);
+        spatial_hash.query(
+            bullet_transform.translation.truncate(),
+            BULLET_HEIGHT / 2.0,
+            &mut candidates,
+        );
+        for (rock_entity, rock_transform, rock) in rock_query.iter_many(candidates.iter()) {
+            if pending_destruction.is_pending(rock_entity) {
+                continue;
+            }
             if collide(
                 bullet_transform.translation,
                 Vec2::new(BULLET_WIDTH, BULLET_HEIGHT),
@@ -130,16 +292,36 @@ pub fn bullet_rock_collision(
             )
             .is_some()
             {
-                commands.entity(rock_entity).despawn();
-                commands.entity(bullet_entity).despawn();
-                score.value += 25;
+                pending_destruction.mark(rock_entity);
+                rock_pool.release(&mut commands, rock_entity);
+                score.add(25);
                 explosion_event_writer.send(SpawnExplosion {
                     pos: rock_transform.translation,
+                    kind: rock.explosion(),
+                });
+                mineral_event_writer.send(DropMinerals {
+                    pos: rock_transform.translation,
+                    amount: rock.minerals(),
+                });
+                sound_event_writer.send(PlaySound {
+                    effect: SoundEffect::RockHit,
+                    pos: Some(rock_transform.translation.truncate()),
+                });
+                bullet_hit_event_writer.send(BulletHit {
+                    pos: bullet_transform.translation.truncate(),
                 });
-                audio.play(audio_handles.rock_collison.clone());
                 rocks_destroyed.count += 1;
+                remaining -= 1;
+                if remaining == 0 {
+                    pending_destruction.mark(bullet_entity);
+                    bullet_pool.release(&mut commands, bullet_entity);
+                    break;
+                }
             }
         }
+        if let Some(bullet_damage) = bullet_damage.as_mut() {
+            bullet_damage.remaining = remaining;
+        }
     }
 }
 
@@ -147,20 +329,170 @@ pub struct GunPlugin;
 
 impl Plugin for GunPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(add_bullet_cooldown_timer_resource.in_schedule(OnEnter(AppState::Game)))
+        app.init_resource::<EntityPool<Bullet>>()
+            .add_event::<BulletHit>()
+            .add_systems(
+                (add_bullet_cooldown_timer_resource, add_gun_stats_resource)
+                    .in_schedule(OnEnter(AppState::Game)),
+            )
             .add_systems(
                 (
-                    shoot,
-                    move_bullets,
-                    despawn_off_screen_bullets,
-                    tick_bullet_cooldown_timer,
-                    bullet_rock_collision,
+                    tick_bullet_cooldown_timer.in_set(GameSet::Timers),
+                    shoot.after(spawn_crates).in_set(GameSet::Spawn),
+                    move_bullets.in_set(GameSet::Movement),
+                    detonate_bomb.in_set(GameSet::Collision),
+                    bullet_rock_collision
+                        .after(update_rock_spatial_hash)
+                        .after(detonate_bomb)
+                        .in_set(GameSet::Collision),
+                    despawn_off_screen_bullets
+                        .after(bullet_crate_collision)
+                        .after(resolve_detonations)
+                        .in_set(GameSet::Collision),
                 )
                     .in_set(OnUpdate(AppState::Game)),
             )
             .add_systems(
-                (remove_bullet_cooldown_timer_resource, despawn_all_bullets)
+                (
+                    remove_bullet_cooldown_timer_resource,
+                    remove_gun_stats_resource,
+                    despawn_all_bullets,
+                )
                     .in_schedule(OnExit(AppState::Game)),
             );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arena::ARENA_HEIGHT,
+        headless::{enter_game, headless_app},
+        player::DamagePlayer,
+        rock::{RockSize, RockSpeed},
+    };
+    use bevy::input::{keyboard::KeyboardInput, ButtonState};
+
+    fn spawn_bullet(app: &mut App, position: Vec3) {
+        app.world
+            .spawn((Transform::from_translation(position), Bullet {}));
+    }
+
+    fn spawn_rock(app: &mut App, position: Vec3) {
+        app.world.spawn((
+            Transform::from_translation(position),
+            Rock {
+                rock_size: RockSize::Big,
+                rock_speed: RockSpeed::Slow,
+            },
+        ));
+    }
+
+    fn count<T: Component>(app: &mut App) -> usize {
+        app.world
+            .query_filtered::<(), With<T>>()
+            .iter(&app.world)
+            .count()
+    }
+
+    #[test]
+    fn two_bullets_destroy_one_rock_once() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        spawn_rock(&mut app, Vec3::new(200.0, 700.0, 0.0));
+        spawn_bullet(&mut app, Vec3::new(200.0, 700.0, 0.0));
+        spawn_bullet(&mut app, Vec3::new(202.0, 700.0, 0.0));
+
+        app.update();
+
+        assert_eq!(app.world.resource::<RocksDestroyed>().count, 1);
+        assert_eq!(app.world.resource::<Score>().value, 25);
+        assert_eq!(count::<Rock>(&mut app), 0);
+        assert_eq!(count::<Bullet>(&mut app), 1);
+    }
+
+    #[test]
+    fn one_bullet_destroys_only_one_of_two_overlapping_rocks() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        spawn_rock(&mut app, Vec3::new(200.0, 700.0, 0.0));
+        spawn_rock(&mut app, Vec3::new(205.0, 700.0, 0.0));
+        spawn_bullet(&mut app, Vec3::new(202.0, 700.0, 0.0));
+
+        app.update();
+
+        assert_eq!(app.world.resource::<RocksDestroyed>().count, 1);
+        assert_eq!(app.world.resource::<Score>().value, 25);
+        assert_eq!(count::<Rock>(&mut app), 1);
+        assert_eq!(count::<Bullet>(&mut app), 0);
+        assert_eq!(app.world.resource::<EntityPool<Rock>>().stats().pooled, 1);
+    }
+
+    #[test]
+    fn double_damage_bullet_breaks_two_overlapping_rocks_with_double_score() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        let mut active_effects = app.world.resource_mut::<ActiveEffects>();
+        active_effects.apply(EffectKind::DoubleDamage);
+        active_effects.apply(EffectKind::DoubleScore);
+        app.update();
+        let score = app.world.resource::<Score>().value;
+        spawn_rock(&mut app, Vec3::new(200.0, 700.0, 0.0));
+        spawn_rock(&mut app, Vec3::new(205.0, 700.0, 0.0));
+        app.world.spawn((
+            Transform::from_xyz(202.0, 700.0, 0.0),
+            Bullet {},
+            BulletDamage { remaining: 2 },
+        ));
+
+        app.update();
+
+        assert_eq!(app.world.resource::<RocksDestroyed>().count, 2);
+        assert_eq!(app.world.resource::<Score>().value, score + 2 * 2 * 25);
+        assert_eq!(count::<Rock>(&mut app), 0);
+        assert_eq!(count::<Bullet>(&mut app), 0);
+    }
+
+    #[test]
+    fn bullet_and_player_hitting_the_same_rock_resolve_it_once() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        let position = app
+            .world
+            .query_filtered::<&Transform, With<Player>>()
+            .single(&app.world)
+            .translation;
+        spawn_rock(&mut app, position);
+        spawn_bullet(&mut app, position);
+
+        app.update();
+
+        assert_eq!(app.world.resource::<RocksDestroyed>().count, 1);
+        assert_eq!(app.world.resource::<Score>().value, 25);
+        assert!(app.world.resource::<Events<DamagePlayer>>().is_empty());
+        assert_eq!(count::<Bullet>(&mut app), 0);
+        assert_eq!(app.world.resource::<EntityPool<Rock>>().stats().pooled, 1);
+    }
+
+    #[test]
+    fn bomb_clears_rocks_inside_the_arena() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        spawn_rock(&mut app, Vec3::new(200.0, 700.0, 0.0));
+        spawn_rock(&mut app, Vec3::new(400.0, 500.0, 0.0));
+        spawn_rock(&mut app, Vec3::new(300.0, ARENA_HEIGHT + 100.0, 0.0));
+
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::B),
+            state: ButtonState::Pressed,
+        });
+        app.update();
+
+        assert_eq!(app.world.resource::<RocksDestroyed>().count, 2);
+        assert_eq!(count::<Rock>(&mut app), 1);
+        let player = app.world.query::<&Player>().single(&app.world);
+        assert_eq!(player.bombs, 0);
+    }
+}
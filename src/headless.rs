@@ -0,0 +1,274 @@
+use std::{fmt, time::Duration};
+
+use bevy::{ecs::schedule::ExecutorKind, input::InputPlugin, prelude::*, time::TimePlugin};
+
+use crate::{
+    assets::{SpriteAssets, UiAssets},
+    autopilot::Autopilot,
+    rng::GameRng,
+    score::{Highscore, Score},
+    state::AppState,
+    GamePlugin,
+};
+
+pub const HEADLESS_TICK: f32 = 1.0 / 60.0;
+/// Soak test games that outlive this many ticks (ten minutes) are cut short.
+pub const SOAK_MAX_TICKS: u32 = 10 * 60 * 60;
+
+/// Advances `Time` by exactly one tick per update so headless runs do not
+/// depend on how fast the machine executes them.
+fn advance_fixed_time(mut time: ResMut<Time>) {
+    let last_update = time.last_update().unwrap_or_else(|| time.startup());
+    time.update_with_instant(last_update + Duration::from_secs_f32(HEADLESS_TICK));
+}
+
+/// Replaces the window, renderer, audio and asset server with the bare
+/// minimum the gameplay systems need. Asset handles are left as defaults.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MinimalPlugins.build().disable::<TimePlugin>())
+            .add_plugin(InputPlugin)
+            .init_resource::<Time>()
+            .init_resource::<SpriteAssets>()
+            .init_resource::<UiAssets>()
+            .add_system(advance_fixed_time.in_base_set(CoreSet::First));
+    }
+}
+
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugin(HeadlessPlugin).add_plugin(GamePlugin);
+    // The multithreaded executor may run unordered systems in a different
+    // order every tick, which would make seeded runs diverge.
+    for (_, schedule) in app.world.resource_mut::<Schedules>().iter_mut() {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    }
+    app
+}
+
+/// Leaves the main menu and runs the tick that enters `AppState::Game`.
+pub fn enter_game(app: &mut App) {
+    app.update();
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Game);
+    app.update();
+}
+
+pub fn app_state(app: &App) -> AppState {
+    app.world.resource::<State<AppState>>().0.clone()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SoakReport {
+    pub games: u32,
+    pub seed: u64,
+    pub average_survival_seconds: f32,
+    pub average_score: f32,
+    pub best_score: u32,
+    pub timeouts: u32,
+}
+
+impl fmt::Display for SoakReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "games:            {} (seed {})", self.games, self.seed)?;
+        writeln!(f, "average survival: {:.1}s", self.average_survival_seconds)?;
+        writeln!(f, "average score:    {:.1}", self.average_score)?;
+        writeln!(f, "best score:       {}", self.best_score)?;
+        write!(f, "timed out:        {}", self.timeouts)
+    }
+}
+
+/// Lets the autopilot play `games` headless runs, seeding game `n` with `seed + n`.
+pub fn run_soak_test(games: u32, seed: u64) -> SoakReport {
+    let mut total_ticks: u64 = 0;
+    let mut total_score: u64 = 0;
+    let mut best_score = 0;
+    let mut timeouts = 0;
+
+    for game in 0..games {
+        let mut app = headless_app();
+        app.insert_resource(GameRng::seeded(seed.wrapping_add(game as u64)))
+            .insert_resource(Autopilot {
+                enabled: true,
+                attract_mode: false,
+            });
+        enter_game(&mut app);
+
+        let mut ticks = 0;
+        while app_state(&app) == AppState::Game && ticks < SOAK_MAX_TICKS {
+            app.update();
+            ticks += 1;
+        }
+        if ticks == SOAK_MAX_TICKS {
+            timeouts += 1;
+        }
+
+        let score = match app.world.get_resource::<Score>() {
+            Some(score) => score.value,
+            None => app.world.resource::<Highscore>().value,
+        };
+        total_ticks += ticks as u64;
+        total_score += score as u64;
+        best_score = best_score.max(score);
+    }
+
+    let games_played = games.max(1) as f32;
+    SoakReport {
+        games,
+        seed,
+        average_survival_seconds: total_ticks as f32 * HEADLESS_TICK / games_played,
+        average_score: total_score as f32 / games_played,
+        best_score,
+        timeouts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arena::{ArenaLayout, ARENA_WIDTH, WIDE_ARENA_WIDTH},
+        player::{Player, PLAYER_STARTING_HEALTH},
+        rock::{Rock, RockSize, RockSpeed, RocksDestroyed},
+        settings::Settings,
+        space_crates::{CrateType, SpaceCrate},
+    };
+    use bevy::input::{keyboard::KeyboardInput, ButtonState};
+
+    fn run_ticks(app: &mut App, ticks: u32) {
+        for _ in 0..ticks {
+            app.update();
+        }
+    }
+
+    fn player(app: &mut App) -> (Entity, Vec3, f32) {
+        let (entity, transform, player) = app
+            .world
+            .query::<(Entity, &Transform, &Player)>()
+            .single(&app.world);
+        (entity, transform.translation, player.health)
+    }
+
+    fn spawn_rock_on_player(app: &mut App, rock_size: RockSize) {
+        let (_, position, _) = player(app);
+        app.world.spawn((
+            Transform::from_translation(position),
+            Rock {
+                rock_size,
+                rock_speed: RockSpeed::Fast,
+            },
+        ));
+    }
+
+    #[test]
+    fn entering_the_game_spawns_a_fresh_player() {
+        let mut app = headless_app();
+        assert_eq!(app_state(&app), AppState::MainMenu);
+
+        enter_game(&mut app);
+
+        assert_eq!(app_state(&app), AppState::Game);
+        assert_eq!(player(&mut app).2, PLAYER_STARTING_HEALTH);
+        assert_eq!(app.world.resource::<RocksDestroyed>().count, 0);
+    }
+
+    #[test]
+    fn arena_layouts_size_the_run_and_classic_wraps_the_ship() {
+        let mut app = headless_app();
+        app.world.resource_mut::<Settings>().arena = ArenaLayout::Widescreen;
+        enter_game(&mut app);
+        assert_eq!(player(&mut app).1.x, WIDE_ARENA_WIDTH / 2.0);
+
+        let mut app = headless_app();
+        app.world.resource_mut::<Settings>().arena = ArenaLayout::Classic;
+        enter_game(&mut app);
+        let (entity, _, _) = player(&mut app);
+        app.world
+            .get_mut::<Transform>(entity)
+            .unwrap()
+            .translation
+            .x = 1.0;
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::Left),
+            state: ButtonState::Pressed,
+        });
+        run_ticks(&mut app, 2);
+        assert!(player(&mut app).1.x > ARENA_WIDTH / 2.0);
+    }
+
+    #[test]
+    fn score_ticks_up_while_playing() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+
+        run_ticks(&mut app, 3 * 60 + 1);
+
+        assert_eq!(app.world.resource::<Score>().value, 15);
+    }
+
+    #[test]
+    fn rock_collision_damages_player_and_scores() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        spawn_rock_on_player(&mut app, RockSize::Small);
+
+        run_ticks(&mut app, 2);
+
+        assert_eq!(player(&mut app).2, PLAYER_STARTING_HEALTH - 10.0);
+        assert_eq!(app.world.resource::<RocksDestroyed>().count, 1);
+        assert_eq!(app.world.resource::<Score>().value, 25);
+    }
+
+    #[test]
+    fn health_crate_heals_damaged_player() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        spawn_rock_on_player(&mut app, RockSize::Big);
+        run_ticks(&mut app, 2);
+
+        let (_, position, _) = player(&mut app);
+        app.world.spawn((
+            Transform::from_translation(position),
+            SpaceCrate {
+                crate_type: CrateType::Health,
+            },
+        ));
+        run_ticks(&mut app, 2);
+
+        assert_eq!(player(&mut app).2, PLAYER_STARTING_HEALTH - 30.0 + 20.0);
+    }
+
+    #[test]
+    fn dying_returns_to_main_menu_and_keeps_highscore() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        let (entity, _, _) = player(&mut app);
+        app.world.get_mut::<Player>(entity).unwrap().health = 1.0;
+        spawn_rock_on_player(&mut app, RockSize::Small);
+
+        run_ticks(&mut app, 3);
+
+        assert_eq!(app_state(&app), AppState::MainMenu);
+        assert!(!app.world.contains_resource::<Score>());
+        assert_eq!(app.world.resource::<Highscore>().value, 25);
+        assert_eq!(
+            app.world
+                .query_filtered::<(), With<Player>>()
+                .iter(&app.world)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn soak_test_is_reproducible_for_a_seed() {
+        let report = run_soak_test(2, 29);
+
+        assert_eq!(report, run_soak_test(2, 29));
+        assert!(report.average_survival_seconds > 0.0);
+    }
+}
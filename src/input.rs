@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+use crate::autopilot::Autopilot;
+
+/// What the ship should do this tick, regardless of who is flying it.
+#[derive(Resource, Default)]
+pub struct PlayerActions {
+    pub movement: Vec2,
+    pub shoot: bool,
+    /// Only set on the tick the bomb button goes down.
+    pub bomb: bool,
+}
+
+/// Systems that fill in `PlayerActions`. Anything reading the actions runs after this set.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ActionSet;
+
+fn read_keyboard_actions(keyboard_input: Res<Input<KeyCode>>, mut actions: ResMut<PlayerActions>) {
+    let mut movement = Vec2::ZERO;
+
+    if keyboard_input.pressed(KeyCode::Left) || keyboard_input.pressed(KeyCode::A) {
+        movement.x -= 1.0;
+    }
+    if keyboard_input.pressed(KeyCode::Right) || keyboard_input.pressed(KeyCode::D) {
+        movement.x += 1.0;
+    }
+    if keyboard_input.pressed(KeyCode::Up) || keyboard_input.pressed(KeyCode::W) {
+        movement.y += 1.0;
+    }
+    if keyboard_input.pressed(KeyCode::Down) || keyboard_input.pressed(KeyCode::S) {
+        movement.y -= 1.0;
+    }
+
+    actions.movement = movement;
+    actions.shoot = keyboard_input.pressed(KeyCode::Space);
+    actions.bomb = keyboard_input.just_pressed(KeyCode::B);
+}
+
+fn autopilot_disabled(autopilot: Res<Autopilot>) -> bool {
+    !autopilot.enabled
+}
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerActions>().add_system(
+            read_keyboard_actions
+                .run_if(autopilot_disabled)
+                .in_set(ActionSet),
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use bevy::prelude::*;
+
+use crate::{
+    assets::SpriteAssets,
+    effects::{ActiveEffects, EffectKind},
+    player::{player_movement, Player},
+    state::{AppState, GameSet},
+};
+
+pub const MAGNET_RADIUS: f32 = 300.0;
+pub const MAGNET_PULL_SPEED: f32 = 250.0;
+/// Width in pixels of the radius indicator sprite.
+pub const MAGNET_RING_SIZE: f32 = 64.0;
+
+/// Things the magnet pulls toward the ship.
+#[derive(Component)]
+pub struct Magnetic;
+
+#[derive(Component)]
+pub struct MagnetIndicator;
+
+/// The magnet's strength and, while it is switched on, where it pulls toward.
+#[derive(Resource)]
+pub struct Magnet {
+    pub radius: f32,
+    pub pull_speed: f32,
+    /// Set by upgrades that keep the magnet on without the timed effect.
+    pub permanent: bool,
+    center: Option<Vec2>,
+}
+
+impl Default for Magnet {
+    fn default() -> Self {
+        Magnet {
+            radius: MAGNET_RADIUS,
+            pull_speed: MAGNET_PULL_SPEED,
+            permanent: false,
+            center: None,
+        }
+    }
+}
+
+impl Magnet {
+    /// Whether something at `pos` is being pulled this tick. Systems that
+    /// move magnetic entities on their own skip those that are.
+    pub fn pulls(&self, pos: Vec2) -> bool {
+        self.center
+            .is_some_and(|center| center.distance(pos) < self.radius)
+    }
+}
+
+pub fn update_magnet(
+    mut magnet: ResMut<Magnet>,
+    active_effects: Res<ActiveEffects>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    let switched_on = magnet.permanent || active_effects.is_active(EffectKind::Magnet);
+    magnet.center = match player_query.get_single() {
+        Ok(transform) if switched_on => Some(transform.translation.truncate()),
+        _ => None,
+    };
+}
+
+pub fn attract_magnetic(
+    magnet: Res<Magnet>,
+    mut magnetic_query: Query<&mut Transform, With<Magnetic>>,
+    time: Res<Time>,
+) {
+    let Some(center) = magnet.center else {
+        return;
+    };
+    for mut transform in magnetic_query.iter_mut() {
+        let pos = transform.translation.truncate();
+        if magnet.pulls(pos) {
+            let pull =
+                (center - pos).normalize_or_zero() * magnet.pull_speed * time.delta_seconds();
+            transform.translation += pull.extend(0.0);
+        }
+    }
+}
+
+pub fn spawn_magnet_indicator(mut commands: Commands, handles: Res<SpriteAssets>) {
+    commands.spawn((
+        SpriteBundle {
+            texture: handles.magnet_ring.clone(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        MagnetIndicator {},
+    ));
+}
+
+pub fn update_magnet_indicator(
+    magnet: Res<Magnet>,
+    mut indicator_query: Query<(&mut Transform, &mut Visibility), With<MagnetIndicator>>,
+) {
+    for (mut transform, mut visibility) in indicator_query.iter_mut() {
+        match magnet.center {
+            Some(center) => {
+                *visibility = Visibility::Visible;
+                transform.translation = center.extend(-2.0);
+                transform.scale = Vec3::splat(2.0 * magnet.radius / MAGNET_RING_SIZE);
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+pub fn despawn_magnet_indicator(
+    mut commands: Commands,
+    indicator_query: Query<Entity, With<MagnetIndicator>>,
+) {
+    for entity in indicator_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn add_magnet_resource(mut commands: Commands) {
+    commands.insert_resource(Magnet::default())
+}
+
+pub fn remove_magnet_resource(mut commands: Commands) {
+    commands.remove_resource::<Magnet>();
+}
+
+pub struct MagnetPlugin;
+
+impl Plugin for MagnetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (add_magnet_resource, spawn_magnet_indicator).in_schedule(OnEnter(AppState::Game)),
+        )
+        .add_systems(
+            (
+                update_magnet.after(player_movement),
+                attract_magnetic.after(update_magnet),
+                update_magnet_indicator.after(update_magnet),
+            )
+                .in_set(GameSet::Movement)
+                .in_set(OnUpdate(AppState::Game)),
+        )
+        .add_systems(
+            (remove_magnet_resource, despawn_magnet_indicator).in_schedule(OnExit(AppState::Game)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        headless::{enter_game, headless_app},
+        space_crates::{CrateLootTable, CrateSpawnTimer, CrateType, SpaceCrate},
+    };
+
+    #[test]
+    fn magnet_pulls_crates_in_range_instead_of_letting_them_fall() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        app.world
+            .resource_mut::<ActiveEffects>()
+            .apply(EffectKind::Magnet);
+        let player = app
+            .world
+            .query_filtered::<&Transform, With<Player>>()
+            .single(&app.world)
+            .translation;
+        let near = player + Vec3::new(100.0, 0.0, 0.0);
+        let far = player + Vec3::new(0.0, MAGNET_RADIUS + 100.0, 0.0);
+        for position in [near, far] {
+            app.world.spawn((
+                Transform::from_translation(position),
+                SpaceCrate {
+                    crate_type: CrateType::ScoreBonus,
+                },
+                Magnetic {},
+            ));
+        }
+
+        app.update();
+
+        let mut positions: Vec<Vec3> = app
+            .world
+            .query_filtered::<&Transform, With<SpaceCrate>>()
+            .iter(&app.world)
+            .map(|transform| transform.translation)
+            .collect();
+        positions.sort_by(|a, b| a.y.total_cmp(&b.y));
+        assert_eq!(positions[0].y, near.y);
+        assert!(positions[0].x < near.x);
+        assert_eq!(positions[1].x, far.x);
+        assert!(positions[1].y < far.y);
+    }
+
+    #[test]
+    fn explosive_crates_in_range_keep_falling() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        app.world
+            .resource_mut::<ActiveEffects>()
+            .apply(EffectKind::Magnet);
+        app.world
+            .insert_resource(CrateLootTable::new(vec![(CrateType::Explosive, 1)]));
+        let mut crate_spawn_timer = app.world.resource_mut::<CrateSpawnTimer>();
+        let duration = crate_spawn_timer.timer.duration();
+        crate_spawn_timer.timer.set_elapsed(duration);
+        app.update();
+
+        let player = app
+            .world
+            .query_filtered::<&Transform, With<Player>>()
+            .single(&app.world)
+            .translation;
+        let near = player + Vec3::new(100.0, 0.0, 0.0);
+        let (entity, mut transform) = app
+            .world
+            .query_filtered::<(Entity, &mut Transform), With<SpaceCrate>>()
+            .single_mut(&mut app.world);
+        transform.translation = near;
+
+        app.update();
+
+        let position = app.world.get::<Transform>(entity).unwrap().translation;
+        assert_eq!(position.x, near.x);
+        assert!(position.y < near.y);
+    }
+}
@@ -1,75 +1,200 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+mod animation;
 mod arena;
 mod assets;
+mod autopilot;
+mod blast;
+mod camera_shake;
+mod debug;
+mod destruction;
+mod effects;
 mod explosion;
 mod gun;
+mod headless;
+mod input;
+mod magnet;
+mod minerals;
+mod music;
+mod nebula;
+mod particles;
+mod pickups;
 mod player;
+mod pool;
+mod rng;
 mod rock;
+mod run_upgrades;
+mod save;
 mod score;
+mod settings;
+mod shop;
+mod sound;
 mod space_crates;
+mod spatial_hash;
 mod star;
 mod state;
 mod ui;
+mod viewport;
+mod widgets;
 
 use crate::{
-    arena::{ArenaPlugin, ARENA_HEIGHT, ARENA_WIDTH},
+    animation::AnimationPlugin,
+    arena::ArenaPlugin,
     assets::AssetsPlugin,
+    autopilot::AutopilotPlugin,
+    blast::BlastPlugin,
+    camera_shake::{Impact, ScreenShakePlugin},
+    debug::DebugPlugin,
+    destruction::DestructionPlugin,
+    effects::EffectsPlugin,
     explosion::ExplosionPlugin,
     gun::GunPlugin,
+    headless::run_soak_test,
+    input::{ActionPlugin, ActionSet},
+    magnet::MagnetPlugin,
+    minerals::MineralPlugin,
+    music::MusicPlugin,
+    nebula::NebulaPlugin,
+    particles::ParticlePlugin,
+    pickups::PickupPlugin,
     player::PlayerPlugin,
+    rng::GameRng,
     rock::RockPlugin,
+    run_upgrades::RunUpgradePlugin,
+    save::{SaveData, SaveFile, SavePlugin, SAVE_FILE},
     score::ScorePlugin,
+    settings::{Settings, SettingsFile, SettingsPlugin, SETTINGS_FILE},
+    shop::ShopPlugin,
+    sound::{PlaySound, SoundPlugin},
     space_crates::CratePlugin,
     star::StarPlugin,
-    state::{go_to_main_menu, start_game, AppState},
+    state::{go_to_main_menu, AppState, GameSet, RunState},
     ui::MenuPlugin,
+    viewport::ViewportPlugin,
+    widgets::WidgetPlugin,
 };
 
-use bevy::{
-    prelude::*,
-    window::PresentMode,
-};
+use bevy::prelude::*;
 
 pub const CLEAR_COLOR: Color = Color::rgb(0.0, 0.0, 0.2);
 pub const ASSET_SCALE: f32 = 4.0;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--soak") {
+        let games = args
+            .get(index + 1)
+            .and_then(|games| games.parse().ok())
+            .unwrap_or(100);
+        let seed = args
+            .iter()
+            .position(|arg| arg == "--seed")
+            .and_then(|index| args.get(index + 1))
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or(0);
+        println!("{}", run_soak_test(games, seed));
+        return;
+    }
+
+    let settings = Settings::load(SETTINGS_FILE);
+    let arena = settings.arena.arena();
     App::new()
-        .add_state::<AppState>()
         .insert_resource(ClearColor(CLEAR_COLOR))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "SG029".into(),
-                resolution: (ARENA_WIDTH, ARENA_HEIGHT).into(),
-                present_mode: PresentMode::AutoNoVsync,
+                resolution: (arena.width, arena.height).into(),
+                resizable: true,
+                present_mode: settings.present_mode(),
+                mode: settings.display_mode.window_mode(),
                 ..default()
             }),
             ..default()
         }))
         .add_plugin(AssetsPlugin)
+        // Audio and visuals only. Headless runs play the same game without them.
+        .add_plugin(SoundPlugin)
+        .add_plugin(MusicPlugin)
         .add_plugin(ArenaPlugin)
-        .add_plugin(PlayerPlugin)
+        .add_plugin(NebulaPlugin)
         .add_plugin(StarPlugin)
-        .add_plugin(RockPlugin)
-        .add_plugin(CratePlugin)
-        .add_plugin(ScorePlugin)
-        .add_plugin(GunPlugin)
-        .add_plugin(ExplosionPlugin)
-        .add_plugin(MenuPlugin)
+        .add_plugin(ParticlePlugin)
+        .add_plugin(ScreenShakePlugin)
+        .add_plugin(ViewportPlugin)
+        .insert_resource(SaveData::load(SAVE_FILE))
+        .insert_resource(SaveFile {
+            path: Some(SAVE_FILE.into()),
+        })
+        .insert_resource(arena)
+        .insert_resource(settings)
+        .insert_resource(SettingsFile {
+            path: Some(SETTINGS_FILE.into()),
+        })
+        .add_plugin(GamePlugin)
         .add_startup_system(setup)
-        .add_system(start_game)
-        .add_system(go_to_main_menu)
         .run();
 }
 
-pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// Everything the game needs to run a simulation. Windowing, asset loading,
+/// audio output and purely decorative sprites are added on top of this in
+/// `main`, which lets `headless::headless_app` run the same game in tests.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<AppState>()
+            .add_state::<RunState>()
+            .add_event::<PlaySound>()
+            .add_event::<Impact>()
+            .init_resource::<GameRng>()
+            .configure_sets(
+                (
+                    ActionSet,
+                    GameSet::Timers,
+                    GameSet::Spawn,
+                    GameSet::Movement,
+                    GameSet::Collision,
+                    GameSet::Effects,
+                )
+                    .chain(),
+            )
+            .configure_set(ActionSet.run_if(in_state(RunState::Playing)))
+            .configure_set(GameSet::Timers.run_if(in_state(RunState::Playing)))
+            .configure_set(GameSet::Spawn.run_if(in_state(RunState::Playing)))
+            .configure_set(GameSet::Movement.run_if(in_state(RunState::Playing)))
+            .configure_set(GameSet::Collision.run_if(in_state(RunState::Playing)))
+            .configure_set(GameSet::Effects.run_if(in_state(RunState::Playing)))
+            .add_plugin(SavePlugin)
+            .add_plugin(SettingsPlugin)
+            .add_plugin(ActionPlugin)
+            .add_plugin(AutopilotPlugin)
+            .add_plugin(DestructionPlugin)
+            .add_plugin(PlayerPlugin)
+            .add_plugin(RockPlugin)
+            .add_plugin(CratePlugin)
+            .add_plugin(PickupPlugin)
+            .add_plugin(MagnetPlugin)
+            .add_plugin(MineralPlugin)
+            .add_plugin(EffectsPlugin)
+            .add_plugin(ScorePlugin)
+            .add_plugin(GunPlugin)
+            .add_plugin(BlastPlugin)
+            .add_plugin(AnimationPlugin)
+            .add_plugin(ExplosionPlugin)
+            .add_plugin(WidgetPlugin)
+            .add_plugin(MenuPlugin)
+            .add_plugin(ShopPlugin)
+            .add_plugin(RunUpgradePlugin)
+            .add_plugin(DebugPlugin)
+            .add_system(go_to_main_menu);
+    }
+}
+
+/// The camera starts at the origin and is moved onto the arena by
+/// `viewport::center_camera_on_arena`.
+pub fn setup(mut commands: Commands) {
     commands.spawn(Camera2dBundle {
-        transform: Transform::from_xyz(ARENA_WIDTH / 2.0, ARENA_HEIGHT / 2.0, 5.0),
-        ..default()
-    });
-    commands.spawn(SpriteBundle {
-        transform: Transform::from_xyz(ARENA_WIDTH / 2.0, ARENA_HEIGHT / 2.0, -3.0),
-        texture: asset_server.load("sprites/background.png"),
+        transform: Transform::from_xyz(0.0, 0.0, 5.0),
         ..default()
     });
 }
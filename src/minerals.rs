@@ -0,0 +1,251 @@
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+use rand::prelude::*;
+
+use crate::{
+    animation::{AnimationMode, SpriteAnimation},
+    assets::SpriteAssets,
+    autopilot::Autopilot,
+    destruction::PendingDestruction,
+    effects::ActiveEffects,
+    magnet::{attract_magnetic, Magnet, Magnetic},
+    player::{player_crate_collision, Player, PLAYER_SIZE},
+    rng::GameRng,
+    save::SaveData,
+    sound::{PlaySound, SoundEffect},
+    state::{AppState, GameSet},
+    ASSET_SCALE,
+};
+
+pub const MINERAL_SIZE: f32 = 6.0 * ASSET_SCALE;
+/// Seconds a mineral floats around before it is lost.
+pub const MINERAL_LIFETIME: f32 = 6.0;
+pub const MINERAL_FALL_SPEED: f32 = 40.0;
+pub const MINERAL_SCATTER_SPEED: f32 = 150.0;
+/// Fraction of the scatter velocity lost per second.
+pub const MINERAL_DRAG: f32 = 1.5;
+pub const MINERAL_FRAMES: usize = 4;
+pub const MINERAL_GLINT_SECONDS: f32 = 0.15;
+
+/// Sent when a rock is shot apart, `amount` minerals are scattered around `pos`.
+pub struct DropMinerals {
+    pub pos: Vec3,
+    pub amount: u32,
+}
+
+#[derive(Component)]
+pub struct Mineral {
+    pub velocity: Vec2,
+    pub lifetime: Timer,
+}
+
+/// Minerals collected this run, banked into the wallet when it ends.
+#[derive(Resource, Default)]
+pub struct RunMinerals {
+    pub collected: u32,
+}
+
+pub fn spawn_mineral_drops(
+    mut commands: Commands,
+    mut event_reader: EventReader<DropMinerals>,
+    handles: Res<SpriteAssets>,
+    mut rng: ResMut<GameRng>,
+) {
+    for event in event_reader.iter() {
+        for _ in 0..event.amount {
+            let angle = rng.gen::<f32>() * std::f32::consts::TAU;
+            let speed = MINERAL_SCATTER_SPEED * (0.5 + rng.gen::<f32>() / 2.0);
+            commands.spawn((
+                SpriteSheetBundle {
+                    transform: Transform::from_xyz(event.pos.x, event.pos.y, 1.0).with_scale(
+                        Vec3 {
+                            x: ASSET_SCALE,
+                            y: ASSET_SCALE,
+                            ..default()
+                        },
+                    ),
+                    texture_atlas: handles.mineral_sheet.clone(),
+                    ..default()
+                },
+                SpriteAnimation::new(
+                    0,
+                    MINERAL_FRAMES - 1,
+                    MINERAL_GLINT_SECONDS,
+                    AnimationMode::Loop,
+                ),
+                Mineral {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    lifetime: Timer::from_seconds(MINERAL_LIFETIME, TimerMode::Once),
+                },
+                Magnetic {},
+            ));
+        }
+    }
+}
+
+pub fn expire_minerals(
+    mut commands: Commands,
+    mut mineral_query: Query<(Entity, &mut Mineral)>,
+    mut pending_destruction: ResMut<PendingDestruction>,
+    time: Res<Time>,
+) {
+    for (entity, mut mineral) in mineral_query.iter_mut() {
+        if mineral.lifetime.tick(time.delta()).finished() && pending_destruction.mark(entity) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub fn move_minerals(
+    mut mineral_query: Query<(&mut Transform, &mut Mineral)>,
+    active_effects: Res<ActiveEffects>,
+    magnet: Res<Magnet>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds() * active_effects.world_speed();
+    for (mut transform, mut mineral) in mineral_query.iter_mut() {
+        if magnet.pulls(transform.translation.truncate()) {
+            continue;
+        }
+        let velocity = mineral.velocity - Vec2::new(0.0, MINERAL_FALL_SPEED);
+        transform.translation += (velocity * delta).extend(0.0);
+        mineral.velocity *= (1.0 - MINERAL_DRAG * delta).max(0.0);
+    }
+}
+
+pub fn collect_minerals(
+    mut commands: Commands,
+    player_query: Query<&Transform, With<Player>>,
+    mineral_query: Query<(Entity, &Transform), With<Mineral>>,
+    mut pending_destruction: ResMut<PendingDestruction>,
+    mut run_minerals: ResMut<RunMinerals>,
+    mut sound_event_writer: EventWriter<PlaySound>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    for (entity, transform) in mineral_query.iter() {
+        if collide(
+            player_transform.translation,
+            Vec2::new(PLAYER_SIZE, PLAYER_SIZE),
+            transform.translation,
+            Vec2::new(MINERAL_SIZE, MINERAL_SIZE),
+        )
+        .is_some()
+            && pending_destruction.mark(entity)
+        {
+            commands.entity(entity).despawn();
+            run_minerals.collected += 1;
+            sound_event_writer.send(PlaySound {
+                effect: SoundEffect::CollectMineral,
+                pos: Some(transform.translation.truncate()),
+            });
+        }
+    }
+}
+
+/// Moves this run's minerals into the wallet. Attract mode runs do not count.
+pub fn bank_minerals(
+    run_minerals: Res<RunMinerals>,
+    mut save_data: ResMut<SaveData>,
+    autopilot: Res<Autopilot>,
+) {
+    if run_minerals.collected > 0 && !autopilot.attract_mode {
+        save_data.wallet += run_minerals.collected;
+    }
+}
+
+pub fn despawn_minerals(mut commands: Commands, mineral_query: Query<Entity, With<Mineral>>) {
+    for entity in mineral_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn add_run_minerals_resource(mut commands: Commands) {
+    commands.insert_resource(RunMinerals::default())
+}
+
+pub fn remove_run_minerals_resource(mut commands: Commands) {
+    commands.remove_resource::<RunMinerals>();
+}
+
+pub struct MineralPlugin;
+
+impl Plugin for MineralPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DropMinerals>()
+            .add_system(add_run_minerals_resource.in_schedule(OnEnter(AppState::Game)))
+            .add_systems(
+                (
+                    expire_minerals.in_set(GameSet::Timers),
+                    move_minerals
+                        .after(attract_magnetic)
+                        .in_set(GameSet::Movement),
+                    collect_minerals
+                        .after(player_crate_collision)
+                        .in_set(GameSet::Collision),
+                    spawn_mineral_drops.in_set(GameSet::Effects),
+                )
+                    .in_set(OnUpdate(AppState::Game)),
+            )
+            .add_systems(
+                (
+                    bank_minerals,
+                    remove_run_minerals_resource.after(bank_minerals),
+                    despawn_minerals,
+                )
+                    .in_schedule(OnExit(AppState::Game)),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gun::Bullet,
+        headless::{app_state, enter_game, headless_app},
+        player::DamagePlayer,
+        rock::{Rock, RockSize, RockSpeed},
+    };
+
+    #[test]
+    fn shot_rocks_drop_minerals_that_are_banked_when_the_run_ends() {
+        let mut app = headless_app();
+        enter_game(&mut app);
+        let (player, position) = {
+            let (entity, transform) = app
+                .world
+                .query_filtered::<(Entity, &Transform), With<Player>>()
+                .single(&app.world);
+            (entity, transform.translation)
+        };
+        let rock_position = position + Vec3::new(0.0, 300.0, 0.0);
+        app.world.spawn((
+            Transform::from_translation(rock_position),
+            Rock {
+                rock_size: RockSize::Big,
+                rock_speed: RockSpeed::Slow,
+            },
+        ));
+        app.world
+            .spawn((Transform::from_translation(rock_position), Bullet {}));
+
+        app.update();
+        app.update();
+        let minerals = app
+            .world
+            .query_filtered::<(), With<Mineral>>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(minerals, 3);
+
+        app.world.resource_mut::<RunMinerals>().collected = 7;
+        app.world.get_mut::<Player>(player).unwrap().health = 1.0;
+        app.world.send_event(DamagePlayer { damage: 1.0 });
+        app.update();
+        app.update();
+
+        assert_eq!(app_state(&app), AppState::MainMenu);
+        assert_eq!(app.world.resource::<SaveData>().wallet, 7);
+    }
+}
@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+
+use crate::{
+    assets::AudioAssets,
+    explosion::{ExplosionKind, SpawnExplosion},
+    rock::DifficultyStage,
+    settings::Settings,
+    sound::VolumeChannel,
+    state::{AppState, GameSet, RunState},
+};
+
+pub const MUSIC_VOLUME: f32 = 0.5;
+pub const MUSIC_CROSSFADE_SECONDS: f32 = 2.0;
+/// Share of the music volume left right after a big explosion.
+pub const DUCK_VOLUME: f32 = 0.35;
+pub const DUCK_RECOVERY_SECONDS: f32 = 1.2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicTrack {
+    Menu,
+    Cruise,
+    Pressure,
+    Onslaught,
+}
+
+impl MusicTrack {
+    /// The run gets louder and faster as the difficulty stages go by.
+    pub fn for_stage(stage: usize) -> MusicTrack {
+        match stage {
+            0 | 1 => MusicTrack::Cruise,
+            2 | 3 => MusicTrack::Pressure,
+            _ => MusicTrack::Onslaught,
+        }
+    }
+}
+
+impl AudioAssets {
+    fn music(&self, track: MusicTrack) -> Handle<AudioSource> {
+        match track {
+            MusicTrack::Menu => self.music_menu.clone(),
+            MusicTrack::Cruise => self.music_cruise.clone(),
+            MusicTrack::Pressure => self.music_pressure.clone(),
+            MusicTrack::Onslaught => self.music_onslaught.clone(),
+        }
+    }
+}
+
+/// A looping track and how far it has faded in, from 0 to 1.
+struct MusicVoice {
+    track: MusicTrack,
+    sink: Handle<AudioSink>,
+    fade: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct Music {
+    playing: Option<MusicVoice>,
+    fading_out: Vec<MusicVoice>,
+    /// How far the music is ducked, 1 right after a big explosion.
+    duck: f32,
+}
+
+/// How far a track has faded after `delta` more seconds, rising to 1 while it
+/// fades in and falling to 0 while it fades out.
+pub fn crossfade(fade: f32, delta: f32, fading_in: bool) -> f32 {
+    let step = delta / MUSIC_CROSSFADE_SECONDS;
+    if fading_in {
+        (fade + step).min(1.0)
+    } else {
+        (fade - step).max(0.0)
+    }
+}
+
+/// `volume` lowered by how far the music is ducked.
+pub fn ducked(volume: f32, duck: f32) -> f32 {
+    volume * (1.0 + (DUCK_VOLUME - 1.0) * duck)
+}
+
+pub fn choose_music_track(
+    mut music: ResMut<Music>,
+    app_state: Res<State<AppState>>,
+    difficulty_stage: Option<Res<DifficultyStage>>,
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    handles: Res<AudioAssets>,
+) {
+    let track = match (&app_state.0, difficulty_stage) {
+        (AppState::Game, Some(difficulty_stage)) => MusicTrack::for_stage(difficulty_stage.stage),
+        _ => MusicTrack::Menu,
+    };
+    if music.playing.as_ref().map(|voice| voice.track) == Some(track) {
+        return;
+    }
+    let sink = audio.play_with_settings(
+        handles.music(track),
+        PlaybackSettings::LOOP.with_volume(0.0),
+    );
+    let voice = MusicVoice {
+        track,
+        sink: audio_sinks.get_handle(sink),
+        fade: 0.0,
+    };
+    if let Some(previous) = music.playing.replace(voice) {
+        music.fading_out.push(previous);
+    }
+}
+
+pub fn duck_music(
+    mut music: ResMut<Music>,
+    mut explosion_event_reader: EventReader<SpawnExplosion>,
+) {
+    if explosion_event_reader
+        .iter()
+        .any(|event| matches!(event.kind, ExplosionKind::Crate | ExplosionKind::Player))
+    {
+        music.duck = 1.0;
+    }
+}
+
+/// Fades and ducks in real time so hit-stop and slow motion leave the music alone.
+pub fn mix_music(
+    mut music: ResMut<Music>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    run_state: Res<State<RunState>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let delta = time.raw_delta_seconds();
+    music.duck = (music.duck - delta / DUCK_RECOVERY_SECONDS).max(0.0);
+    let volume = ducked(
+        MUSIC_VOLUME * settings.volumes.output(VolumeChannel::Music),
+        music.duck,
+    );
+    let on_hold = run_state.0 == RunState::ChoosingUpgrade;
+
+    if let Some(voice) = music.playing.as_mut() {
+        voice.fade = crossfade(voice.fade, delta, true);
+        // The sink only exists once the track has loaded.
+        if let Some(sink) = audio_sinks.get(&voice.sink) {
+            sink.set_volume(volume * voice.fade);
+            let hold = on_hold && voice.track != MusicTrack::Menu;
+            if hold && !sink.is_paused() {
+                sink.pause();
+            } else if !hold && sink.is_paused() {
+                sink.play();
+            }
+        }
+    }
+
+    music.fading_out.retain_mut(|voice| {
+        voice.fade = crossfade(voice.fade, delta, false);
+        // Keep waiting for a track that has not loaded yet, or it would
+        // start up silently later and never be stopped.
+        let Some(sink) = audio_sinks.get(&voice.sink) else {
+            return true;
+        };
+        if voice.fade <= 0.0 {
+            sink.stop();
+            return false;
+        }
+        sink.set_volume(volume * voice.fade);
+        true
+    });
+}
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Music>().add_systems(
+            (choose_music_track, duck_music, mix_music)
+                .chain()
+                .after(GameSet::Effects),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_step_the_music_up_from_cruise_to_onslaught() {
+        let tracks: Vec<MusicTrack> = (0..=6).map(MusicTrack::for_stage).collect();
+        assert_eq!(
+            tracks,
+            vec![
+                MusicTrack::Cruise,
+                MusicTrack::Cruise,
+                MusicTrack::Pressure,
+                MusicTrack::Pressure,
+                MusicTrack::Onslaught,
+                MusicTrack::Onslaught,
+                MusicTrack::Onslaught,
+            ]
+        );
+    }
+
+    #[test]
+    fn crossfades_ramp_over_the_fade_time_and_stay_in_range() {
+        let half = MUSIC_CROSSFADE_SECONDS / 2.0;
+        let incoming = crossfade(0.0, half, true);
+        let outgoing = crossfade(1.0, half, false);
+        assert_eq!(incoming, 0.5);
+        assert_eq!(outgoing, 0.5);
+        assert_eq!(crossfade(incoming, MUSIC_CROSSFADE_SECONDS, true), 1.0);
+        assert_eq!(crossfade(outgoing, MUSIC_CROSSFADE_SECONDS, false), 0.0);
+
+        assert_eq!(ducked(0.8, 0.0), 0.8);
+        assert!((ducked(1.0, 1.0) - DUCK_VOLUME).abs() < 1e-6);
+    }
+}
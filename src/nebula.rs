@@ -0,0 +1,349 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+use rand::prelude::*;
+
+use crate::{
+    arena::Arena,
+    rock::{StageReached, DIFFICULTY_STAGES},
+    state::AppState,
+    ASSET_SCALE,
+};
+
+pub const NEBULA_Z: f32 = -3.0;
+/// How long a newly generated region of space takes to fade in.
+pub const NEBULA_FADE_SECONDS: f32 = 3.0;
+/// Nebula noise lattice cells across the width of the image.
+pub const NEBULA_FREQUENCY: f32 = 4.0;
+pub const NEBULA_OCTAVES: u32 = 5;
+pub const MAX_PLANETS: u32 = 2;
+
+/// Colors of one region of space.
+pub struct NebulaPalette {
+    pub space: Color,
+    pub clouds: [Color; 2],
+    pub planet: Color,
+}
+
+/// One palette for the start of a run and one for every difficulty stage.
+pub const NEBULA_PALETTES: [NebulaPalette; DIFFICULTY_STAGES.len() + 1] = [
+    NebulaPalette {
+        space: Color::rgb(0.01, 0.01, 0.04),
+        clouds: [Color::rgb(0.1, 0.15, 0.45), Color::rgb(0.3, 0.1, 0.4)],
+        planet: Color::rgb(0.45, 0.55, 0.7),
+    },
+    NebulaPalette {
+        space: Color::rgb(0.01, 0.03, 0.03),
+        clouds: [Color::rgb(0.05, 0.35, 0.35), Color::rgb(0.1, 0.3, 0.15)],
+        planet: Color::rgb(0.7, 0.6, 0.4),
+    },
+    NebulaPalette {
+        space: Color::rgb(0.04, 0.01, 0.04),
+        clouds: [Color::rgb(0.45, 0.1, 0.35), Color::rgb(0.2, 0.05, 0.45)],
+        planet: Color::rgb(0.5, 0.7, 0.55),
+    },
+    NebulaPalette {
+        space: Color::rgb(0.04, 0.02, 0.01),
+        clouds: [Color::rgb(0.5, 0.25, 0.05), Color::rgb(0.45, 0.08, 0.05)],
+        planet: Color::rgb(0.4, 0.45, 0.6),
+    },
+    NebulaPalette {
+        space: Color::rgb(0.04, 0.0, 0.0),
+        clouds: [Color::rgb(0.55, 0.05, 0.05), Color::rgb(0.35, 0.0, 0.15)],
+        planet: Color::rgb(0.6, 0.35, 0.3),
+    },
+];
+
+/// A background sprite that is still fading in over the previous one.
+#[derive(Component)]
+pub struct FadingNebula {
+    timer: Timer,
+}
+
+#[derive(Component)]
+pub struct Nebula;
+
+/// A region of space still being painted off the main thread.
+#[derive(Component)]
+pub struct GeneratingNebula {
+    task: Task<Image>,
+}
+
+/// Random value in 0..1 for a point of the noise lattice.
+fn lattice(seed: u64, x: i32, y: i32) -> f32 {
+    let mut hash = seed ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    hash ^= (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash = (hash ^ (hash >> 31)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash ^= hash >> 29;
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn value_noise(seed: u64, pos: Vec2) -> f32 {
+    let cell = pos.floor();
+    let (x, y) = (cell.x as i32, cell.y as i32);
+    let t = pos - cell;
+    let t = t * t * (Vec2::splat(3.0) - 2.0 * t);
+    let top = lattice(seed, x, y) + (lattice(seed, x + 1, y) - lattice(seed, x, y)) * t.x;
+    let bottom =
+        lattice(seed, x, y + 1) + (lattice(seed, x + 1, y + 1) - lattice(seed, x, y + 1)) * t.x;
+    top + (bottom - top) * t.y
+}
+
+/// Fractal noise in 0..1, each octave twice as fine and half as strong.
+fn fbm(seed: u64, pos: Vec2) -> f32 {
+    let mut total = 0.0;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.0;
+    for octave in 0..NEBULA_OCTAVES {
+        total += amplitude * value_noise(seed.wrapping_add(octave as u64), pos * frequency);
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    total / (1.0 - 0.5f32.powi(NEBULA_OCTAVES as i32))
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp_color(from: Vec3, to: Vec3, amount: f32) -> Vec3 {
+    from + (to - from) * amount
+}
+
+fn rgb(color: Color) -> Vec3 {
+    let [r, g, b, _] = color.as_rgba_f32();
+    Vec3::new(r, g, b)
+}
+
+struct Planet {
+    center: Vec2,
+    radius: f32,
+    /// Seed of the bands across the planet.
+    bands: u64,
+}
+
+/// Paints nebula clouds and a few distant planets in the colors of `palette`.
+/// The same seed and palette always give the same image.
+pub fn generate_nebula(seed: u64, palette: &NebulaPalette, width: u32, height: u32) -> Image {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let planets: Vec<Planet> = (0..rng.gen_range(0..=MAX_PLANETS))
+        .map(|_| Planet {
+            center: Vec2::new(
+                rng.gen_range(0.0..width as f32),
+                rng.gen_range(0.0..height as f32),
+            ),
+            radius: rng.gen_range(6.0..24.0),
+            bands: rng.gen(),
+        })
+        .collect();
+    let light = Vec3::new(-0.6, 0.5, 0.6).normalize();
+    let space = rgb(palette.space);
+    let clouds = palette.clouds.map(rgb);
+    let planet_color = rgb(palette.planet);
+    let scale = NEBULA_FREQUENCY / width as f32;
+
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let pos = Vec2::new(x as f32, y as f32);
+            // Warping the lookup by another noise gives wispy rather than blobby clouds.
+            let warp = Vec2::new(
+                fbm(seed ^ 0x11, pos * scale),
+                fbm(seed ^ 0x22, pos * scale + 5.2),
+            );
+            let density = smoothstep(0.4, 0.8, fbm(seed, pos * scale + warp * 1.5));
+            let hue = fbm(seed ^ 0x33, pos * scale * 0.5);
+            let cloud = lerp_color(clouds[0], clouds[1], smoothstep(0.3, 0.7, hue));
+            let mut color = lerp_color(space, cloud, density * 0.8);
+
+            for planet in &planets {
+                let offset = (pos - planet.center) / planet.radius;
+                let distance_squared = offset.length_squared();
+                if distance_squared < 1.0 {
+                    let normal = offset.extend((1.0 - distance_squared).sqrt());
+                    let lit = normal.dot(light).max(0.0);
+                    let band = value_noise(planet.bands, Vec2::new(0.0, offset.y * 4.0));
+                    let surface = planet_color * (0.7 + 0.3 * band) * (0.15 + 0.85 * lit);
+                    // Planets are far away, so a little of the nebula shows through.
+                    color = lerp_color(color, surface, 0.85);
+                }
+            }
+
+            let [r, g, b] = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.0)
+                .round()
+                .to_array();
+            data.extend_from_slice(&[r as u8, g as u8, b as u8, 255]);
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler_descriptor = ImageSampler::nearest();
+    image
+}
+
+/// Starts painting the region for `stage` on the async compute pool, so a
+/// stage change never stalls a frame. Regions still being painted are
+/// dropped, which cancels them, so an older one can never finish last.
+fn start_nebula(
+    commands: &mut Commands,
+    generating_query: &Query<Entity, With<GeneratingNebula>>,
+    arena: &Arena,
+    stage: usize,
+) {
+    for entity in generating_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let palette_index = stage.min(NEBULA_PALETTES.len() - 1);
+    // The background is generated at the same pixel size as the sprites.
+    let size = (arena.size() / ASSET_SCALE).ceil();
+    let seed = random();
+    let task = AsyncComputeTaskPool::get().spawn(async move {
+        generate_nebula(
+            seed,
+            &NEBULA_PALETTES[palette_index],
+            size.x as u32,
+            size.y as u32,
+        )
+    });
+    commands.spawn(GeneratingNebula { task });
+}
+
+/// Every run starts in a fresh region of space.
+pub fn generate_first_region(
+    mut commands: Commands,
+    generating_query: Query<Entity, With<GeneratingNebula>>,
+    arena: Res<Arena>,
+) {
+    start_nebula(&mut commands, &generating_query, &arena, 0);
+}
+
+pub fn generate_stage_region(
+    mut commands: Commands,
+    mut event_reader: EventReader<StageReached>,
+    generating_query: Query<Entity, With<GeneratingNebula>>,
+    arena: Res<Arena>,
+) {
+    if let Some(event) = event_reader.iter().last() {
+        start_nebula(&mut commands, &generating_query, &arena, event.stage);
+    }
+}
+
+/// Turns finished regions into sprites that fade in over the current one.
+pub fn show_generated_nebulae(
+    mut commands: Commands,
+    mut generating_query: Query<(Entity, &mut GeneratingNebula)>,
+    mut images: ResMut<Assets<Image>>,
+    arena: Res<Arena>,
+) {
+    for (entity, mut generating) in generating_query.iter_mut() {
+        let Some(image) = future::block_on(future::poll_once(&mut generating.task)) else {
+            continue;
+        };
+        commands
+            .entity(entity)
+            .remove::<GeneratingNebula>()
+            .insert((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1.0, 1.0, 1.0, 0.0),
+                        ..default()
+                    },
+                    transform: Transform {
+                        // Draw over the region it replaces until it has faded in.
+                        translation: arena.center().extend(NEBULA_Z + 0.1),
+                        scale: Vec3::new(ASSET_SCALE, ASSET_SCALE, 1.0),
+                        ..default()
+                    },
+                    texture: images.add(image),
+                    ..default()
+                },
+                Nebula {},
+                FadingNebula {
+                    timer: Timer::from_seconds(NEBULA_FADE_SECONDS, TimerMode::Once),
+                },
+            ));
+    }
+}
+
+/// Fades new regions in and drops the ones they cover once they are opaque,
+/// which also frees their images.
+pub fn fade_nebulae(
+    mut commands: Commands,
+    mut fading_query: Query<(Entity, &mut Sprite, &mut Transform, &mut FadingNebula)>,
+    nebula_query: Query<Entity, (With<Nebula>, Without<FadingNebula>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut sprite, mut transform, mut fading) in fading_query.iter_mut() {
+        fading.timer.tick(time.delta());
+        sprite.color.set_a(fading.timer.percent());
+        if fading.timer.finished() {
+            for old_entity in nebula_query.iter() {
+                commands.entity(old_entity).despawn();
+            }
+            transform.translation.z = NEBULA_Z;
+            commands.entity(entity).remove::<FadingNebula>();
+        }
+    }
+}
+
+pub struct NebulaPlugin;
+
+impl Plugin for NebulaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(generate_first_region.in_schedule(OnEnter(AppState::MainMenu)))
+            .add_system(generate_stage_region.in_set(OnUpdate(AppState::Game)))
+            .add_systems((show_generated_nebulae, fade_nebulae).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_are_repeatable_per_seed_and_differ_per_stage() {
+        let first = generate_nebula(7, &NEBULA_PALETTES[0], 32, 32);
+        let again = generate_nebula(7, &NEBULA_PALETTES[0], 32, 32);
+        let other_seed = generate_nebula(8, &NEBULA_PALETTES[0], 32, 32);
+        let other_stage = generate_nebula(7, &NEBULA_PALETTES[4], 32, 32);
+        assert_eq!(first.data.len(), 32 * 32 * 4);
+        assert_eq!(first.data, again.data);
+        assert_ne!(first.data, other_seed.data);
+        assert_ne!(first.data, other_stage.data);
+    }
+
+    #[test]
+    fn starting_a_region_cancels_the_one_still_generating() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).init_resource::<Arena>();
+        let mut system = IntoSystem::into_system(generate_first_region);
+        system.initialize(&mut app.world);
+        for _ in 0..2 {
+            system.run((), &mut app.world);
+            system.apply_buffers(&mut app.world);
+        }
+
+        let generating = app
+            .world
+            .query_filtered::<(), With<GeneratingNebula>>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(generating, 1);
+    }
+}
@@ -0,0 +1,373 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::{
+    explosion::{ExplosionKind, SpawnExplosion},
+    gun::BulletHit,
+    player::Player,
+    pool::EntityPool,
+    space_crates::CratePickup,
+    state::RunState,
+    ASSET_SCALE,
+};
+
+/// Exhaust draws behind the ship but in front of the stars at -1.
+pub const EXHAUST_Z: f32 = -0.5;
+/// Debris and sparks draw over rocks, crates and minerals but under explosions at 2.
+pub const DEBRIS_Z: f32 = 1.5;
+/// Exhaust particles per second while the ship is alive.
+pub const EXHAUST_RATE: f32 = 40.0;
+
+/// How the particles of one emitter or burst look and move. Particles are
+/// untextured squares whose color and size blend from start to end over
+/// their lifetime.
+#[derive(Clone, Debug)]
+pub struct ParticleConfig {
+    pub lifetime: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Angle in radians particles are sent toward, 0 being to the right.
+    pub direction: f32,
+    /// Half angle of the cone around `direction`, `PI` sends them every way.
+    pub spread: f32,
+    pub gravity: Vec2,
+    pub start_color: Color,
+    pub end_color: Color,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub z: f32,
+}
+
+impl ParticleConfig {
+    pub const ROCK_DEBRIS: ParticleConfig = ParticleConfig {
+        lifetime: 0.6,
+        min_speed: 60.0,
+        max_speed: 220.0,
+        direction: 0.0,
+        spread: PI,
+        gravity: Vec2::new(0.0, -200.0),
+        start_color: Color::rgb(0.55, 0.5, 0.45),
+        end_color: Color::rgba(0.3, 0.27, 0.25, 0.0),
+        start_size: 1.5 * ASSET_SCALE,
+        end_size: 0.5 * ASSET_SCALE,
+        z: DEBRIS_Z,
+    };
+
+    pub const SPARKS: ParticleConfig = ParticleConfig {
+        lifetime: 0.25,
+        min_speed: 150.0,
+        max_speed: 350.0,
+        direction: -FRAC_PI_2,
+        spread: 0.9,
+        gravity: Vec2::ZERO,
+        start_color: Color::rgb(1.0, 0.98, 0.55),
+        end_color: Color::rgba(1.0, 0.5, 0.2, 0.0),
+        start_size: 0.75 * ASSET_SCALE,
+        end_size: 0.25 * ASSET_SCALE,
+        z: DEBRIS_Z,
+    };
+
+    pub const EXHAUST: ParticleConfig = ParticleConfig {
+        lifetime: 0.35,
+        min_speed: 80.0,
+        max_speed: 140.0,
+        direction: -FRAC_PI_2,
+        spread: 0.25,
+        gravity: Vec2::ZERO,
+        start_color: Color::rgb(0.6, 0.85, 1.0),
+        end_color: Color::rgba(0.2, 0.3, 0.9, 0.0),
+        start_size: ASSET_SCALE,
+        end_size: 0.25 * ASSET_SCALE,
+        z: EXHAUST_Z,
+    };
+
+    pub const PICKUP: ParticleConfig = ParticleConfig {
+        lifetime: 0.5,
+        min_speed: 100.0,
+        max_speed: 180.0,
+        direction: 0.0,
+        spread: PI,
+        gravity: Vec2::ZERO,
+        start_color: Color::rgb(1.0, 0.9, 0.4),
+        end_color: Color::rgba(1.0, 1.0, 1.0, 0.0),
+        start_size: ASSET_SCALE,
+        end_size: 0.5 * ASSET_SCALE,
+        z: DEBRIS_Z,
+    };
+}
+
+/// Continuously emits particles from the entity it is attached to.
+#[derive(Component)]
+pub struct ParticleEmitter {
+    pub config: ParticleConfig,
+    /// Particles per second.
+    pub rate: f32,
+    /// Where particles appear relative to the entity.
+    pub offset: Vec2,
+    owed: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(config: ParticleConfig, rate: f32, offset: Vec2) -> Self {
+        ParticleEmitter {
+            config,
+            rate,
+            offset,
+            owed: 0.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    age: f32,
+    config: ParticleConfig,
+}
+
+/// Spawns `count` particles at `pos`. Particles are cosmetic and use their
+/// own randomness so they never change how a seeded run plays out.
+pub fn spawn_particles(
+    commands: &mut Commands,
+    particle_pool: &mut EntityPool<Particle>,
+    pos: Vec2,
+    config: &ParticleConfig,
+    count: u32,
+) {
+    let mut rng = thread_rng();
+    for _ in 0..count {
+        let angle = config.direction + rng.gen_range(-1.0..=1.0) * config.spread;
+        let speed = rng.gen_range(config.min_speed..=config.max_speed);
+        particle_pool.acquire(
+            commands,
+            (
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: config.start_color,
+                        custom_size: Some(Vec2::splat(config.start_size)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(pos.extend(config.z)),
+                    ..default()
+                },
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    age: 0.0,
+                    config: config.clone(),
+                },
+            ),
+        );
+    }
+}
+
+pub fn emit_particles(
+    mut commands: Commands,
+    mut particle_pool: ResMut<EntityPool<Particle>>,
+    mut emitter_query: Query<(&Transform, &mut ParticleEmitter)>,
+    time: Res<Time>,
+) {
+    for (transform, mut emitter) in emitter_query.iter_mut() {
+        emitter.owed += emitter.rate * time.delta_seconds();
+        let count = emitter.owed.floor();
+        emitter.owed -= count;
+        let pos = transform.translation.truncate() + emitter.offset;
+        spawn_particles(
+            &mut commands,
+            &mut particle_pool,
+            pos,
+            &emitter.config,
+            count as u32,
+        );
+    }
+}
+
+pub fn update_particles(
+    mut commands: Commands,
+    mut particle_pool: ResMut<EntityPool<Particle>>,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut sprite) in particle_query.iter_mut() {
+        particle.age += delta;
+        let progress = particle.age / particle.config.lifetime;
+        if progress >= 1.0 {
+            particle_pool.release(&mut commands, entity);
+            continue;
+        }
+        let gravity = particle.config.gravity;
+        particle.velocity += gravity * delta;
+        transform.translation += (particle.velocity * delta).extend(0.0);
+
+        let config = &particle.config;
+        let start = Vec4::from(config.start_color.as_rgba_f32());
+        let end = Vec4::from(config.end_color.as_rgba_f32());
+        sprite.color = Color::from(start.lerp(end, progress));
+        let size = config.start_size + (config.end_size - config.start_size) * progress;
+        sprite.custom_size = Some(Vec2::splat(size));
+    }
+}
+
+pub fn attach_exhaust(mut commands: Commands, player_query: Query<Entity, Added<Player>>) {
+    for entity in player_query.iter() {
+        commands.entity(entity).insert(ParticleEmitter::new(
+            ParticleConfig::EXHAUST,
+            EXHAUST_RATE,
+            Vec2::new(0.0, -6.0 * ASSET_SCALE),
+        ));
+    }
+}
+
+pub fn debris_from_explosions(
+    mut commands: Commands,
+    mut particle_pool: ResMut<EntityPool<Particle>>,
+    mut explosion_event_reader: EventReader<SpawnExplosion>,
+) {
+    for event in explosion_event_reader.iter() {
+        let count = match event.kind {
+            ExplosionKind::SmallRock => 5,
+            ExplosionKind::Rock => 8,
+            ExplosionKind::BigRock => 14,
+            ExplosionKind::Crate => 20,
+            ExplosionKind::Player => 30,
+        };
+        spawn_particles(
+            &mut commands,
+            &mut particle_pool,
+            event.pos.truncate(),
+            &ParticleConfig::ROCK_DEBRIS,
+            count,
+        );
+    }
+}
+
+pub fn sparks_from_bullet_hits(
+    mut commands: Commands,
+    mut particle_pool: ResMut<EntityPool<Particle>>,
+    mut bullet_hit_event_reader: EventReader<BulletHit>,
+) {
+    for event in bullet_hit_event_reader.iter() {
+        spawn_particles(
+            &mut commands,
+            &mut particle_pool,
+            event.pos,
+            &ParticleConfig::SPARKS,
+            6,
+        );
+    }
+}
+
+pub fn burst_from_crate_pickups(
+    mut commands: Commands,
+    mut particle_pool: ResMut<EntityPool<Particle>>,
+    mut pickup_event_reader: EventReader<CratePickup>,
+) {
+    for event in pickup_event_reader.iter() {
+        spawn_particles(
+            &mut commands,
+            &mut particle_pool,
+            event.pos.truncate(),
+            &ParticleConfig::PICKUP,
+            16,
+        );
+    }
+}
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntityPool<Particle>>().add_systems(
+            (
+                attach_exhaust,
+                emit_particles.after(attach_exhaust),
+                debris_from_explosions,
+                sparks_from_bullet_hits,
+                burst_from_crate_pickups,
+                update_particles,
+            )
+                .distributive_run_if(in_state(RunState::Playing)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{enter_game, headless_app};
+
+    fn count_particles(app: &mut App) -> usize {
+        app.world
+            .query_filtered::<(), With<Particle>>()
+            .iter(&app.world)
+            .count()
+    }
+
+    #[test]
+    fn explosions_scatter_debris_that_fades_out() {
+        let mut app = headless_app();
+        app.add_plugin(ParticlePlugin);
+        enter_game(&mut app);
+        let exhausts = app
+            .world
+            .query_filtered::<(), (With<Player>, With<ParticleEmitter>)>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(exhausts, 1);
+        let exhaust = count_particles(&mut app);
+
+        app.world.send_event(SpawnExplosion {
+            pos: Vec3::new(300.0, 300.0, 2.0),
+            kind: ExplosionKind::BigRock,
+        });
+        app.update();
+        assert!(count_particles(&mut app) >= exhaust + 14);
+
+        app.world.resource_mut::<Events<SpawnExplosion>>().clear();
+        for _ in 0..60 {
+            app.update();
+        }
+        let debris_left = app
+            .world
+            .query::<&Particle>()
+            .iter(&app.world)
+            .filter(|particle| particle.config.z == DEBRIS_Z)
+            .count();
+        assert_eq!(debris_left, 0);
+        assert!(app.world.resource::<EntityPool<Particle>>().stats().reused > 0);
+    }
+
+    #[test]
+    fn particles_freeze_while_an_upgrade_is_chosen() {
+        let mut app = headless_app();
+        app.add_plugin(ParticlePlugin);
+        enter_game(&mut app);
+        for _ in 0..5 {
+            app.update();
+        }
+        app.world
+            .resource_mut::<NextState<RunState>>()
+            .set(RunState::ChoosingUpgrade);
+        app.update();
+        let frozen: Vec<Vec3> = app
+            .world
+            .query_filtered::<&Transform, With<Particle>>()
+            .iter(&app.world)
+            .map(|transform| transform.translation)
+            .collect();
+
+        app.update();
+        app.update();
+
+        let now: Vec<Vec3> = app
+            .world
+            .query_filtered::<&Transform, With<Particle>>()
+            .iter(&app.world)
+            .map(|transform| transform.translation)
+            .collect();
+        assert!(!frozen.is_empty());
+        assert_eq!(now, frozen);
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera_shake::Impact,
+    effects::ApplyEffect,
+    explosion::{ExplosionKind, SpawnExplosion},
+    player::{
+        player_crate_collision, DamagePlayer, HealPlayer, Player, UpdatePlayerHealth,
+        PLAYER_MAX_SHIELD,
+    },
+    score::Score,
+    space_crates::{
+        CratePickup, CrateType, CRATE_DAMAGE, CRATE_HEAL, CRATE_SCORE_BONUS, CRATE_TRAUMA,
+    },
+    state::{AppState, GameSet},
+};
+
+fn pickups_of<'a>(
+    event_reader: &'a mut EventReader<CratePickup>,
+    crate_type: CrateType,
+) -> impl Iterator<Item = &'a CratePickup> {
+    event_reader
+        .iter()
+        .filter(move |pickup| pickup.crate_type == crate_type)
+}
+
+fn apply_health_pickups(
+    mut event_reader: EventReader<CratePickup>,
+    mut heal_event_writer: EventWriter<HealPlayer>,
+) {
+    for _ in pickups_of(&mut event_reader, CrateType::Health) {
+        heal_event_writer.send(HealPlayer {
+            healing: CRATE_HEAL,
+        });
+    }
+}
+
+fn apply_explosive_pickups(
+    mut event_reader: EventReader<CratePickup>,
+    mut damage_event_writer: EventWriter<DamagePlayer>,
+    mut explosion_event_writer: EventWriter<SpawnExplosion>,
+    mut impact_event_writer: EventWriter<Impact>,
+) {
+    for pickup in pickups_of(&mut event_reader, CrateType::Explosive) {
+        damage_event_writer.send(DamagePlayer {
+            damage: CRATE_DAMAGE,
+        });
+        explosion_event_writer.send(SpawnExplosion {
+            pos: Vec3::new(pickup.pos.x, pickup.pos.y, 2.0),
+            kind: ExplosionKind::Crate,
+        });
+        impact_event_writer.send(Impact {
+            trauma: CRATE_TRAUMA,
+        });
+    }
+}
+
+fn apply_shield_pickups(
+    mut event_reader: EventReader<CratePickup>,
+    mut player_query: Query<&mut Player>,
+    mut update_health_event_writer: EventWriter<UpdatePlayerHealth>,
+) {
+    if let Ok(mut player) = player_query.get_single_mut() {
+        for _ in pickups_of(&mut event_reader, CrateType::ShieldRecharge) {
+            player.shield = PLAYER_MAX_SHIELD;
+            update_health_event_writer.send(UpdatePlayerHealth {});
+        }
+    }
+}
+
+fn apply_bomb_pickups(
+    mut event_reader: EventReader<CratePickup>,
+    mut player_query: Query<&mut Player>,
+    mut update_health_event_writer: EventWriter<UpdatePlayerHealth>,
+) {
+    if let Ok(mut player) = player_query.get_single_mut() {
+        for _ in pickups_of(&mut event_reader, CrateType::ExtraBomb) {
+            player.bombs += 1;
+            update_health_event_writer.send(UpdatePlayerHealth {});
+        }
+    }
+}
+
+fn apply_score_pickups(mut event_reader: EventReader<CratePickup>, mut score: ResMut<Score>) {
+    for _ in pickups_of(&mut event_reader, CrateType::ScoreBonus) {
+        score.add(CRATE_SCORE_BONUS);
+    }
+}
+
+fn apply_timed_pickups(
+    mut event_reader: EventReader<CratePickup>,
+    mut effect_event_writer: EventWriter<ApplyEffect>,
+) {
+    for pickup in event_reader.iter() {
+        if let Some(kind) = pickup.crate_type.effect() {
+            effect_event_writer.send(ApplyEffect { kind });
+        }
+    }
+}
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                apply_health_pickups,
+                apply_explosive_pickups,
+                apply_shield_pickups,
+                apply_bomb_pickups,
+                apply_score_pickups,
+                apply_timed_pickups,
+            )
+                .chain()
+                .after(player_crate_collision)
+                .in_set(GameSet::Collision)
+                .in_set(OnUpdate(AppState::Game)),
+        );
+    }
+}
@@ -1,11 +1,21 @@
 use crate::{
-    arena::{ARENA_HEIGHT, ARENA_WIDTH},
-    assets::{AudioAssets, SpriteAssets},
-    explosion::SpawnExplosion,
-    rock::{Rock, RocksDestroyed},
+    arena::Arena,
+    assets::SpriteAssets,
+    blast::resolve_detonations,
+    camera_shake::Impact,
+    destruction::PendingDestruction,
+    effects::ActiveEffects,
+    explosion::{ExplosionKind, SpawnExplosion},
+    gun::{bullet_rock_collision, despawn_off_screen_bullets},
+    input::PlayerActions,
+    pool::EntityPool,
+    rock::{remove_off_screen_rocks, update_rock_spatial_hash, Rock, RocksDestroyed},
+    save::SaveData,
     score::Score,
-    space_crates::{SpaceCrate, CRATE_DAMAGE, CRATE_HEAL, CRATE_HEIGHT, CRATE_WIDTH},
-    state::AppState,
+    sound::{PlaySound, SoundEffect},
+    space_crates::{CratePickup, SpaceCrate, CRATE_HEIGHT, CRATE_WIDTH},
+    spatial_hash::SpatialHash,
+    state::{AppState, GameSet},
     ASSET_SCALE,
 };
 
@@ -14,74 +24,90 @@ use bevy::{prelude::*, sprite::collide_aabb::collide};
 pub const PLAYER_SPEED: f32 = 480.0;
 pub const PLAYER_SIZE: f32 = 16.0 * ASSET_SCALE;
 pub const PLAYER_STARTING_HEALTH: f32 = 100.0;
+pub const PLAYER_MAX_SHIELD: f32 = 50.0;
+pub const PLAYER_STARTING_BOMBS: u32 = 1;
+/// Rock damage that shakes the camera at full trauma.
+pub const FULL_TRAUMA_DAMAGE: f32 = 40.0;
 
 #[derive(Component)]
 pub struct Player {
     pub health: f32,
+    pub max_health: f32,
+    /// Absorbs damage before health does.
+    pub shield: f32,
+    pub bombs: u32,
+    pub speed: f32,
+    /// Shield points recharged per second, up to `PLAYER_MAX_SHIELD`.
+    pub shield_regen: f32,
 }
 
 pub struct HealPlayer {
-    healing: f32,
+    pub healing: f32,
 }
 
 pub struct DamagePlayer {
-    damage: f32,
+    pub damage: f32,
 }
 
 pub struct UpdatePlayerHealth;
 
-pub fn spawn_player(mut commands: Commands, handles: Res<SpriteAssets>) {
+pub fn spawn_player(
+    mut commands: Commands,
+    handles: Res<SpriteAssets>,
+    save_data: Res<SaveData>,
+    arena: Res<Arena>,
+) {
+    let upgrades = &save_data.upgrades;
     commands.spawn((
         SpriteBundle {
-            transform: Transform::from_xyz(ARENA_WIDTH / 2.0, ARENA_HEIGHT / 2.0, 0.0).with_scale(
-                Vec3 {
-                    x: ASSET_SCALE,
-                    y: ASSET_SCALE,
-                    ..default()
-                },
-            ),
+            transform: Transform::from_translation(arena.center().extend(0.0)).with_scale(Vec3 {
+                x: ASSET_SCALE,
+                y: ASSET_SCALE,
+                ..default()
+            }),
             texture: handles.player.clone(),
             ..default()
         },
         Player {
-            health: PLAYER_STARTING_HEALTH,
+            health: upgrades.max_health(),
+            max_health: upgrades.max_health(),
+            shield: 0.0,
+            bombs: upgrades.starting_bombs(),
+            speed: upgrades.player_speed(),
+            shield_regen: 0.0,
         },
     ));
 }
 
 pub fn player_movement(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut player_query: Query<&mut Transform, With<Player>>,
+    actions: Res<PlayerActions>,
+    mut player_query: Query<(&mut Transform, &Player)>,
+    active_effects: Res<ActiveEffects>,
+    arena: Res<Arena>,
     time: Res<Time>,
 ) {
-    if let Ok(mut transform) = player_query.get_single_mut() {
-        let mut direction = Vec3::ZERO;
+    if let Ok((mut transform, player)) = player_query.get_single_mut() {
+        let mut direction = actions.movement.extend(0.0);
 
-        if keyboard_input.pressed(KeyCode::Left) || keyboard_input.pressed(KeyCode::A) {
-            direction += Vec3::new(-1.0, 0.0, 0.0);
-        }
-        if keyboard_input.pressed(KeyCode::Right) || keyboard_input.pressed(KeyCode::D) {
-            direction += Vec3::new(1.0, 0.0, 0.0);
-        }
-        if keyboard_input.pressed(KeyCode::Up) || keyboard_input.pressed(KeyCode::W) {
-            direction += Vec3::new(0.0, 1.0, 0.0);
-        }
-        if keyboard_input.pressed(KeyCode::Down) || keyboard_input.pressed(KeyCode::S) {
-            direction += Vec3::new(0.0, -1.0, 0.0);
+        if direction.length() > 1.0 {
+            direction = direction.normalize();
         }
 
-        if direction.length() > 0.0 {
-            direction = direction.normalize();
+        let speed = player.speed * active_effects.player_speed();
+        let mut new_translation = transform.translation + direction * speed * time.delta_seconds();
+
+        if arena.wrap {
+            new_translation.x = new_translation.x.rem_euclid(arena.width);
+            new_translation.y = new_translation.y.rem_euclid(arena.height);
+            transform.translation = new_translation;
+            return;
         }
 
         let half_player_size: f32 = PLAYER_SIZE / 2.0;
         let x_min: f32 = 0.0 + half_player_size;
-        let x_max: f32 = ARENA_WIDTH - half_player_size;
+        let x_max: f32 = arena.width - half_player_size;
         let y_min: f32 = 0.0 + half_player_size;
-        let y_max: f32 = ARENA_HEIGHT - half_player_size;
-
-        let mut new_translation =
-            transform.translation + direction * PLAYER_SPEED * time.delta_seconds();
+        let y_max: f32 = arena.height - half_player_size;
 
         if new_translation.x < x_min {
             new_translation.x = x_min;
@@ -103,20 +129,31 @@ pub fn player_rock_collision(
     mut commands: Commands,
     player_query: Query<&Transform, With<Player>>,
     rock_query: Query<(Entity, &Transform, &Rock), With<Rock>>,
+    spatial_hash: Res<SpatialHash>,
+    mut candidates: Local<Vec<Entity>>,
+    mut rock_pool: ResMut<EntityPool<Rock>>,
+    mut pending_destruction: ResMut<PendingDestruction>,
     mut event_writer: EventWriter<DamagePlayer>,
     mut explosion_event_writer: EventWriter<SpawnExplosion>,
     mut score: ResMut<Score>,
-    audio: Res<Audio>,
-    handles: Res<AudioAssets>,
+    mut sound_event_writer: EventWriter<PlaySound>,
     mut rocks_destroyed: ResMut<RocksDestroyed>,
+    mut impact_event_writer: EventWriter<Impact>,
 ) {
     if let Ok(player_transform) = player_query.get_single() {
-        for (rock_entity, rock_transform, rock) in rock_query.iter() {
+        spatial_hash.query(
+            player_transform.translation.truncate(),
+            PLAYER_SIZE / 2.0,
+            &mut candidates,
+        );
+        for (rock_entity, rock_transform, rock) in rock_query.iter_many(candidates.iter()) {
             let distance = player_transform
                 .translation
                 .distance(rock_transform.translation);
-            if distance < PLAYER_SIZE / 2.0 + rock.size() / 2.0 {
-                score.value += 25;
+            if distance < PLAYER_SIZE / 2.0 + rock.size() / 2.0
+                && pending_destruction.mark(rock_entity)
+            {
+                score.add(25);
                 event_writer.send(DamagePlayer {
                     damage: rock.damage(),
                 });
@@ -126,9 +163,16 @@ pub fn player_rock_collision(
                         rock_transform.translation.y,
                         2.0,
                     ),
+                    kind: rock.explosion(),
+                });
+                sound_event_writer.send(PlaySound {
+                    effect: SoundEffect::RockHit,
+                    pos: Some(rock_transform.translation.truncate()),
+                });
+                impact_event_writer.send(Impact {
+                    trauma: (rock.damage() / FULL_TRAUMA_DAMAGE).min(1.0),
                 });
-                audio.play(handles.rock_collison.clone());
-                commands.entity(rock_entity).despawn();
+                rock_pool.release(&mut commands, rock_entity);
                 rocks_destroyed.count += 1;
             }
         }
@@ -139,15 +183,16 @@ pub fn player_crate_collision(
     mut commands: Commands,
     player_query: Query<&Transform, With<Player>>,
     crate_query: Query<(Entity, &Transform, &SpaceCrate), With<SpaceCrate>>,
-    mut repair_event_writer: EventWriter<HealPlayer>,
-    mut explosive_event_writer: EventWriter<DamagePlayer>,
-    audio: Res<Audio>,
-    handles: Res<AudioAssets>,
+    mut pending_destruction: ResMut<PendingDestruction>,
+    mut pickup_event_writer: EventWriter<CratePickup>,
+    mut sound_event_writer: EventWriter<PlaySound>,
     mut score: ResMut<Score>,
-    mut explosion_event_writer: EventWriter<SpawnExplosion>,
 ) {
     if let Ok(player_transform) = player_query.get_single() {
         for (entity, space_crate_transform, space_crate) in crate_query.iter() {
+            if pending_destruction.is_pending(entity) {
+                continue;
+            }
             if collide(
                 player_transform.translation,
                 Vec2::new(PLAYER_SIZE, PLAYER_SIZE),
@@ -156,30 +201,17 @@ pub fn player_crate_collision(
             )
             .is_some()
             {
-                match space_crate.crate_type {
-                    crate::space_crates::CrateType::Health => {
-                        repair_event_writer.send(HealPlayer {
-                            healing: CRATE_HEAL,
-                        });
-                        audio.play(handles.collect_repair.clone());
-                        commands.entity(entity).despawn();
-                    }
-                    crate::space_crates::CrateType::Explosive => {
-                        explosive_event_writer.send(DamagePlayer {
-                            damage: CRATE_DAMAGE,
-                        });
-                        audio.play(handles.hit_explosive.clone());
-                        commands.entity(entity).despawn();
-                        explosion_event_writer.send(SpawnExplosion {
-                            pos: Vec3::new(
-                                space_crate_transform.translation.x,
-                                space_crate_transform.translation.y,
-                                2.0,
-                            ),
-                        });
-                    }
-                }
-                score.value += 100;
+                pending_destruction.mark(entity);
+                pickup_event_writer.send(CratePickup {
+                    crate_type: space_crate.crate_type,
+                    pos: space_crate_transform.translation,
+                });
+                sound_event_writer.send(PlaySound {
+                    effect: space_crate.crate_type.pickup_sound(),
+                    pos: Some(space_crate_transform.translation.truncate()),
+                });
+                commands.entity(entity).despawn();
+                score.add(100);
             }
         }
     }
@@ -193,8 +225,8 @@ pub fn heal_player(
     if let Ok(mut player) = player_query.get_single_mut() {
         for event in event_reader.iter() {
             player.health += event.healing;
-            if player.health >= 100.0 {
-                player.health = 100.0;
+            if player.health >= player.max_health {
+                player.health = player.max_health;
             }
             update_health_event_writer.send(UpdatePlayerHealth {});
         }
@@ -203,21 +235,47 @@ pub fn heal_player(
 
 pub fn damage_player(
     mut event_reader: EventReader<DamagePlayer>,
-    mut player_query: Query<&mut Player, With<Player>>,
+    mut player_query: Query<(&mut Player, &Transform)>,
     mut update_health_event_writer: EventWriter<UpdatePlayerHealth>,
+    mut explosion_event_writer: EventWriter<SpawnExplosion>,
+    mut impact_event_writer: EventWriter<Impact>,
     mut next_app_state: ResMut<NextState<AppState>>,
 ) {
-    if let Ok(mut player) = player_query.get_single_mut() {
+    if let Ok((mut player, transform)) = player_query.get_single_mut() {
         for event in event_reader.iter() {
-            player.health -= event.damage;
+            let was_alive = player.health > 0.0;
+            let absorbed = event.damage.min(player.shield);
+            player.shield -= absorbed;
+            player.health -= event.damage - absorbed;
             update_health_event_writer.send(UpdatePlayerHealth {});
             if player.health <= 0.0 {
+                if was_alive {
+                    explosion_event_writer.send(SpawnExplosion {
+                        pos: transform.translation.truncate().extend(2.0),
+                        kind: ExplosionKind::Player,
+                    });
+                    impact_event_writer.send(Impact { trauma: 1.0 });
+                }
                 next_app_state.set(AppState::MainMenu);
             }
         }
     }
 }
 
+pub fn regenerate_shield(
+    mut player_query: Query<&mut Player>,
+    mut update_health_event_writer: EventWriter<UpdatePlayerHealth>,
+    time: Res<Time>,
+) {
+    if let Ok(mut player) = player_query.get_single_mut() {
+        if player.shield_regen > 0.0 && player.shield < PLAYER_MAX_SHIELD {
+            player.shield =
+                (player.shield + player.shield_regen * time.delta_seconds()).min(PLAYER_MAX_SHIELD);
+            update_health_event_writer.send(UpdatePlayerHealth {});
+        }
+    }
+}
+
 pub fn despawn_player(mut commands: Commands, player_query: Query<Entity, With<Player>>) {
     if let Ok(entity) = player_query.get_single() {
         commands.entity(entity).despawn();
@@ -234,11 +292,19 @@ impl Plugin for PlayerPlugin {
             .add_event::<UpdatePlayerHealth>()
             .add_systems(
                 (
-                    player_crate_collision,
-                    player_movement,
-                    player_rock_collision,
-                    damage_player,
-                    heal_player,
+                    regenerate_shield.in_set(GameSet::Timers),
+                    player_movement.in_set(GameSet::Movement),
+                    player_rock_collision
+                        .after(update_rock_spatial_hash)
+                        .after(bullet_rock_collision)
+                        .after(resolve_detonations)
+                        .after(despawn_off_screen_bullets)
+                        .in_set(GameSet::Collision),
+                    player_crate_collision
+                        .after(remove_off_screen_rocks)
+                        .in_set(GameSet::Collision),
+                    heal_player.in_set(GameSet::Effects),
+                    damage_player.after(heal_player).in_set(GameSet::Effects),
                 )
                     .in_set(OnUpdate(AppState::Game)),
             )
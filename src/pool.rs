@@ -0,0 +1,119 @@
+use std::marker::PhantomData;
+
+use bevy::{prelude::*, utils::HashSet};
+
+#[derive(Default, Clone, Copy, Debug)]
+pub struct PoolStats {
+    pub active: usize,
+    pub pooled: usize,
+    pub spawned: usize,
+    pub reused: usize,
+}
+
+/// Recycles entities marked with `T` instead of despawning them.
+///
+/// Released entities are hidden and lose their `T` component, so every
+/// `With<T>` query skips them until they are acquired again.
+#[derive(Resource)]
+pub struct EntityPool<T: Component> {
+    free: Vec<Entity>,
+    /// The same entities as `free`, so releasing one twice is caught
+    /// without scanning the list.
+    pooled: HashSet<Entity>,
+    active: usize,
+    spawned: usize,
+    reused: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> Default for EntityPool<T> {
+    fn default() -> Self {
+        EntityPool {
+            free: Vec::new(),
+            pooled: HashSet::default(),
+            active: 0,
+            spawned: 0,
+            reused: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> EntityPool<T> {
+    /// Inserts `bundle` into a free entity, spawning a new one if the pool is empty.
+    /// The bundle should contain `T` and a fresh `Visibility`.
+    pub fn acquire(&mut self, commands: &mut Commands, bundle: impl Bundle) -> Entity {
+        self.active += 1;
+        match self.free.pop() {
+            Some(entity) => {
+                self.pooled.remove(&entity);
+                self.reused += 1;
+                commands.entity(entity).insert(bundle);
+                entity
+            }
+            None => {
+                self.spawned += 1;
+                commands.spawn(bundle).id()
+            }
+        }
+    }
+
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if !self.pooled.insert(entity) {
+            return;
+        }
+        self.active = self.active.saturating_sub(1);
+        commands
+            .entity(entity)
+            .remove::<T>()
+            .insert(Visibility::Hidden);
+        self.free.push(entity);
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            active: self.active,
+            pooled: self.free.len(),
+            spawned: self.spawned,
+            reused: self.reused,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::CommandQueue;
+
+    #[derive(Component)]
+    struct Pooled;
+
+    #[test]
+    fn releasing_twice_pools_the_entity_once_and_acquire_reuses_it() {
+        let mut world = World::new();
+        let mut pool = EntityPool::<Pooled>::default();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let entity = pool.acquire(&mut commands, (Pooled, Visibility::Visible));
+        queue.apply(&mut world);
+
+        let mut commands = Commands::new(&mut queue, &world);
+        pool.release(&mut commands, entity);
+        pool.release(&mut commands, entity);
+        queue.apply(&mut world);
+        assert_eq!(pool.stats().pooled, 1);
+        assert_eq!(pool.stats().active, 0);
+        assert!(world.get::<Pooled>(entity).is_none());
+
+        let mut commands = Commands::new(&mut queue, &world);
+        assert_eq!(
+            pool.acquire(&mut commands, (Pooled, Visibility::Visible)),
+            entity
+        );
+        queue.apply(&mut world);
+        let mut commands = Commands::new(&mut queue, &world);
+        pool.release(&mut commands, entity);
+        assert_eq!(pool.stats().pooled, 1);
+        assert_eq!(pool.stats().reused, 1);
+    }
+}
@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Source of randomness for everything that affects gameplay, so a run can
+/// be replayed by seeding it.
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(StdRng);
+
+impl GameRng {
+    pub fn seeded(seed: u64) -> Self {
+        GameRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng(StdRng::from_entropy())
+    }
+}
@@ -1,19 +1,35 @@
 use std::time::Duration;
 
 use crate::{
-    arena::{ARENA_HEIGHT, ARENA_WIDTH},
+    arena::{Arena, ARENA_HEIGHT, ARENA_WIDTH},
     assets::SpriteAssets,
-    state::AppState,
+    destruction::PendingDestruction,
+    effects::ActiveEffects,
+    explosion::ExplosionKind,
+    player::player_rock_collision,
+    pool::EntityPool,
+    rng::GameRng,
+    settings::Settings,
+    spatial_hash::{SpatialHash, SPATIAL_HASH_CELL_SIZE},
+    state::{AppState, GameSet},
     ASSET_SCALE,
 };
 use bevy::prelude::*;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub const STARTING_ROCK_COOLDOWN: f32 = 2.0;
 pub const EASY_ROCK_COOLDOWN: f32 = 1.0;
 pub const NORMAL_ROCK_COOLDOWN: f32 = 0.5;
 pub const HARD_ROCK_COOLDOWN: f32 = 0.3;
 pub const VERY_HARD_ROCK_COOLDOWN: f32 = 0.2;
+/// Rocks destroyed after which each difficulty stage starts, and its spawn cooldown.
+pub const DIFFICULTY_STAGES: [(u32, f32); 4] = [
+    (10, EASY_ROCK_COOLDOWN),
+    (25, NORMAL_ROCK_COOLDOWN),
+    (75, HARD_ROCK_COOLDOWN),
+    (200, VERY_HARD_ROCK_COOLDOWN),
+];
 pub const FAST_ROCK_SPEED: f32 = 100.0;
 pub const NORMAL_ROCK_SPEED: f32 = 75.0;
 pub const SLOW_ROCK_SPEED: f32 = 50.0;
@@ -34,14 +50,49 @@ impl Default for RockSpawnTimer {
     }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct RocksDestroyed {
     pub count: u32,
 }
 
-impl Default for RocksDestroyed {
-    fn default() -> Self {
-        RocksDestroyed { count: 0 }
+/// How many of the `DIFFICULTY_STAGES` this run has reached.
+#[derive(Resource, Default)]
+pub struct DifficultyStage {
+    pub stage: usize,
+}
+
+/// Sent when the run moves on to a harder difficulty stage.
+pub struct StageReached {
+    pub stage: usize,
+}
+
+/// How hard runs are from the start, chosen in the settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    /// Multiplier on the rock spawn cooldown of every stage.
+    pub fn cooldown_factor(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 1.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 0.7,
+        }
     }
 }
 
@@ -79,6 +130,24 @@ impl Rock {
             RockSize::Small => SMALL_ROCK_SIZE,
         }
     }
+
+    pub fn explosion(&self) -> ExplosionKind {
+        match self.rock_size {
+            RockSize::Big => ExplosionKind::BigRock,
+            RockSize::Normal => ExplosionKind::Rock,
+            RockSize::Small => ExplosionKind::SmallRock,
+        }
+    }
+
+    /// How many minerals the rock drops when shot apart.
+    pub fn minerals(&self) -> u32 {
+        match self.rock_size {
+            RockSize::Big => 3,
+            RockSize::Normal => 2,
+            RockSize::Small => 1,
+        }
+    }
+
     pub fn damage(&self) -> f32 {
         let base = match self.rock_speed {
             RockSpeed::Fast => 10.0,
@@ -92,35 +161,34 @@ impl Rock {
             RockSize::Small => 1.0,
         };
 
-        return base * multiplier;
+        base * multiplier
     }
 }
 
-fn set_difficulty(
+pub fn set_difficulty(
     rocks_destroyed: Res<RocksDestroyed>,
+    mut difficulty_stage: ResMut<DifficultyStage>,
     mut rock_spawn_timer: ResMut<RockSpawnTimer>,
+    mut stage_event_writer: EventWriter<StageReached>,
+    settings: Res<Settings>,
 ) {
-    if rocks_destroyed.is_changed() {
-        if rocks_destroyed.count > 10 && rocks_destroyed.count <= 25 {
-            rock_spawn_timer
-                .timer
-                .set_duration(Duration::from_secs_f32(EASY_ROCK_COOLDOWN));
-        }
-        if rocks_destroyed.count > 25 && rocks_destroyed.count <= 75 {
-            rock_spawn_timer
-                .timer
-                .set_duration(Duration::from_secs_f32(NORMAL_ROCK_COOLDOWN));
-        }
-        if rocks_destroyed.count > 75 && rocks_destroyed.count <= 200 {
-            rock_spawn_timer
-                .timer
-                .set_duration(Duration::from_secs_f32(HARD_ROCK_COOLDOWN));
-        }
-        if rocks_destroyed.count > 200 && rocks_destroyed.count <= 500 {
-            rock_spawn_timer
-                .timer
-                .set_duration(Duration::from_secs_f32(VERY_HARD_ROCK_COOLDOWN));
+    if !rocks_destroyed.is_changed() {
+        return;
+    }
+    let stage = DIFFICULTY_STAGES
+        .iter()
+        .filter(|(threshold, _)| rocks_destroyed.count > *threshold)
+        .count();
+    if stage > difficulty_stage.stage {
+        // A bomb can clear enough rocks to cross several stages in one tick.
+        for reached in difficulty_stage.stage + 1..=stage {
+            stage_event_writer.send(StageReached { stage: reached });
         }
+        difficulty_stage.stage = stage;
+        let (_, cooldown) = DIFFICULTY_STAGES[stage - 1];
+        rock_spawn_timer.timer.set_duration(Duration::from_secs_f32(
+            cooldown * settings.difficulty.cooldown_factor(),
+        ));
     }
 }
 
@@ -128,12 +196,14 @@ pub fn spawn_rocks_over_time(
     mut commands: Commands,
     handles: Res<SpriteAssets>,
     rock_spawn_timer: Res<RockSpawnTimer>,
+    mut rock_pool: ResMut<EntityPool<Rock>>,
+    mut rng: ResMut<GameRng>,
+    arena: Res<Arena>,
 ) {
     if rock_spawn_timer.timer.finished() {
-        let random_x = random::<f32>() * ARENA_WIDTH;
-        let random_z = random::<f32>() * -1.0;
+        let random_x = rng.gen::<f32>() * arena.width;
+        let random_z = -rng.gen::<f32>();
 
-        let mut rng = thread_rng();
         let (rock_size, rock_sprite) = match rng.gen_range(0..3) {
             0 => (RockSize::Small, handles.small_rock.clone()),
             1 => (RockSize::Normal, handles.normal_rock.clone()),
@@ -146,60 +216,113 @@ pub fn spawn_rocks_over_time(
             _ => RockSpeed::Slow,
         };
 
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform::from_xyz(random_x, BIG_ROCK_SIZE + ARENA_HEIGHT, random_z)
+        rock_pool.acquire(
+            &mut commands,
+            (
+                SpriteBundle {
+                    transform: Transform::from_xyz(
+                        random_x,
+                        BIG_ROCK_SIZE + arena.height,
+                        random_z,
+                    )
                     .with_scale(Vec3 {
                         x: ASSET_SCALE,
                         y: ASSET_SCALE,
                         ..default()
                     }),
-                texture: rock_sprite,
-                ..default()
-            },
-            Rock {
-                rock_size,
-                rock_speed,
-            },
-        ));
+                    texture: rock_sprite,
+                    ..default()
+                },
+                Rock {
+                    rock_size,
+                    rock_speed,
+                },
+            ),
+        );
     }
 }
 
-pub fn move_rocks(mut rock_query: Query<(&mut Transform, &Rock)>, time: Res<Time>) {
+pub fn move_rocks(
+    mut rock_query: Query<(&mut Transform, &Rock)>,
+    active_effects: Res<ActiveEffects>,
+    time: Res<Time>,
+) {
     for (mut transform, rock) in rock_query.iter_mut() {
-        transform.translation.y -= rock.speed() * time.delta_seconds();
+        transform.translation.y -=
+            rock.speed() * active_effects.world_speed() * time.delta_seconds();
+    }
+}
+
+pub fn update_rock_spatial_hash(
+    mut spatial_hash: ResMut<SpatialHash>,
+    rock_query: Query<(Entity, &Transform, &Rock)>,
+) {
+    spatial_hash.clear();
+    for (entity, transform, rock) in rock_query.iter() {
+        spatial_hash.insert(entity, transform.translation.truncate(), rock.size() / 2.0);
     }
 }
 
 pub fn remove_off_screen_rocks(
     mut commands: Commands,
     rock_query: Query<(Entity, &Transform, &Rock), With<Rock>>,
+    mut rock_pool: ResMut<EntityPool<Rock>>,
+    mut pending_destruction: ResMut<PendingDestruction>,
     mut rocks_destroyed: ResMut<RocksDestroyed>,
 ) {
     for (rock_entity, rock_transform, rock) in rock_query.iter() {
-        if rock_transform.translation.y < 0.0 - rock.size() {
-            commands.entity(rock_entity).despawn();
+        if rock_transform.translation.y < 0.0 - rock.size() && pending_destruction.mark(rock_entity)
+        {
+            rock_pool.release(&mut commands, rock_entity);
             rocks_destroyed.count += 1;
         }
     }
 }
 
-pub fn tick_rock_spawn_timer(mut rock_spawn_timer: ResMut<RockSpawnTimer>, time: Res<Time>) {
-    rock_spawn_timer.timer.tick(time.delta());
+pub fn tick_rock_spawn_timer(
+    mut rock_spawn_timer: ResMut<RockSpawnTimer>,
+    active_effects: Res<ActiveEffects>,
+    time: Res<Time>,
+) {
+    rock_spawn_timer
+        .timer
+        .tick(time.delta().mul_f32(active_effects.world_speed()));
 }
 
-pub fn despawn_rocks(mut commands: Commands, rock_query: Query<Entity, With<Rock>>) {
+pub fn despawn_rocks(
+    mut commands: Commands,
+    rock_query: Query<Entity, With<Rock>>,
+    mut rock_pool: ResMut<EntityPool<Rock>>,
+) {
     for entity in rock_query.iter() {
-        commands.entity(entity).despawn();
+        rock_pool.release(&mut commands, entity);
     }
 }
 
-pub fn add_rocks_destroyed_resource(mut commands: Commands) {
-    commands.insert_resource(RocksDestroyed::default())
+pub fn add_rocks_destroyed_resource(mut commands: Commands, settings: Res<Settings>) {
+    commands.insert_resource(RocksDestroyed::default());
+    commands.insert_resource(DifficultyStage::default());
+    // Every run starts over from the first stage's spawn rate.
+    commands.insert_resource(RockSpawnTimer {
+        timer: Timer::from_seconds(
+            STARTING_ROCK_COOLDOWN * settings.difficulty.cooldown_factor(),
+            TimerMode::Repeating,
+        ),
+    });
+}
+
+/// The arena can change shape between runs.
+pub fn fit_spatial_hash_to_arena(mut commands: Commands, arena: Res<Arena>) {
+    commands.insert_resource(SpatialHash::new(
+        arena.width,
+        arena.height,
+        SPATIAL_HASH_CELL_SIZE,
+    ));
 }
 
 pub fn remove_rocks_destoyrd_resource(mut commands: Commands) {
     commands.remove_resource::<RocksDestroyed>();
+    commands.remove_resource::<DifficultyStage>();
 }
 
 pub struct RockPlugin;
@@ -207,14 +330,29 @@ pub struct RockPlugin;
 impl Plugin for RockPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<RockSpawnTimer>()
-            .add_system(add_rocks_destroyed_resource.in_schedule(OnEnter(AppState::Game)))
+            .init_resource::<EntityPool<Rock>>()
+            .add_event::<StageReached>()
+            .insert_resource(SpatialHash::new(
+                ARENA_WIDTH,
+                ARENA_HEIGHT,
+                SPATIAL_HASH_CELL_SIZE,
+            ))
+            .add_systems(
+                (add_rocks_destroyed_resource, fit_spatial_hash_to_arena)
+                    .in_schedule(OnEnter(AppState::Game)),
+            )
             .add_systems(
                 (
-                    spawn_rocks_over_time,
-                    tick_rock_spawn_timer,
-                    move_rocks,
-                    remove_off_screen_rocks,
-                    set_difficulty,
+                    tick_rock_spawn_timer.in_set(GameSet::Timers),
+                    spawn_rocks_over_time.in_set(GameSet::Spawn),
+                    move_rocks.in_set(GameSet::Movement),
+                    update_rock_spatial_hash
+                        .after(move_rocks)
+                        .in_set(GameSet::Movement),
+                    remove_off_screen_rocks
+                        .after(player_rock_collision)
+                        .in_set(GameSet::Collision),
+                    set_difficulty.in_set(GameSet::Effects),
                 )
                     .in_set(OnUpdate(AppState::Game)),
             )
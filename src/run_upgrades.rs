@@ -0,0 +1,454 @@
+use bevy::prelude::*;
+use rand::distributions::{Distribution, WeightedIndex};
+
+use crate::{
+    assets::UiAssets,
+    autopilot::Autopilot,
+    gun::{BulletCooldownTimer, GunStats},
+    magnet::Magnet,
+    minerals::spawn_mineral_drops,
+    player::Player,
+    rng::GameRng,
+    rock::{set_difficulty, StageReached},
+    state::{AppState, GameSet, RunState},
+};
+
+/// How many upgrades are offered each time a difficulty stage is reached.
+pub const UPGRADE_CHOICES: usize = 3;
+pub const FASTER_FIRE_FACTOR: f32 = 0.8;
+pub const SHIELD_REGEN_STEP: f32 = 2.0;
+pub const MAGNET_RADIUS_STEP: f32 = 75.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rarity {
+    Common,
+    Rare,
+    Epic,
+}
+
+impl Rarity {
+    /// Relative odds of an upgrade of this rarity being offered.
+    pub fn weight(&self) -> u32 {
+        match self {
+            Rarity::Common => 6,
+            Rarity::Rare => 3,
+            Rarity::Epic => 1,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rarity::Common => "common",
+            Rarity::Rare => "rare",
+            Rarity::Epic => "epic",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Rarity::Common => Color::rgb(0.8, 0.8, 0.8),
+            Rarity::Rare => Color::rgb(0.4, 0.7, 1.0),
+            Rarity::Epic => Color::rgb(0.9, 0.5, 1.0),
+        }
+    }
+}
+
+/// Upgrades picked between difficulty stages. They only last for the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunUpgrade {
+    ExtraBullet,
+    FasterFire,
+    ShieldRegen,
+    Piercing,
+    BiggerMagnet,
+}
+
+pub struct RunUpgradeDef {
+    pub upgrade: RunUpgrade,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub rarity: Rarity,
+    /// How many times it can be taken in one run.
+    pub max_picks: usize,
+}
+
+/// Everything that can be offered. Change the rarities to change the odds.
+pub const RUN_UPGRADE_POOL: [RunUpgradeDef; 5] = [
+    RunUpgradeDef {
+        upgrade: RunUpgrade::ExtraBullet,
+        name: "Twin barrels",
+        description: "+1 bullet per shot",
+        rarity: Rarity::Epic,
+        max_picks: 2,
+    },
+    RunUpgradeDef {
+        upgrade: RunUpgrade::FasterFire,
+        name: "Overclock",
+        description: "Fire 25% faster",
+        rarity: Rarity::Common,
+        max_picks: 5,
+    },
+    RunUpgradeDef {
+        upgrade: RunUpgrade::ShieldRegen,
+        name: "Shield capacitor",
+        description: "Recharge 2 shield per second",
+        rarity: Rarity::Rare,
+        max_picks: 3,
+    },
+    RunUpgradeDef {
+        upgrade: RunUpgrade::Piercing,
+        name: "Tungsten rounds",
+        description: "Bullets pierce one more rock",
+        rarity: Rarity::Rare,
+        max_picks: 3,
+    },
+    RunUpgradeDef {
+        upgrade: RunUpgrade::BiggerMagnet,
+        name: "Magnet coil",
+        description: "Magnet always on, +75 reach",
+        rarity: Rarity::Common,
+        max_picks: 3,
+    },
+];
+
+impl RunUpgrade {
+    pub fn def(&self) -> &'static RunUpgradeDef {
+        RUN_UPGRADE_POOL
+            .iter()
+            .find(|def| def.upgrade == *self)
+            .expect("every run upgrade is in the pool")
+    }
+
+    pub fn apply(
+        &self,
+        player: &mut Player,
+        gun_stats: &mut GunStats,
+        bullet_cooldown_timer: &mut BulletCooldownTimer,
+        magnet: &mut Magnet,
+    ) {
+        match self {
+            RunUpgrade::ExtraBullet => gun_stats.bullets += 1,
+            RunUpgrade::FasterFire => bullet_cooldown_timer.cooldown *= FASTER_FIRE_FACTOR,
+            RunUpgrade::ShieldRegen => player.shield_regen += SHIELD_REGEN_STEP,
+            RunUpgrade::Piercing => gun_stats.piercing += 1,
+            RunUpgrade::BiggerMagnet => {
+                magnet.permanent = true;
+                magnet.radius += MAGNET_RADIUS_STEP;
+            }
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct RunUpgrades {
+    /// The difficulty stage the current offer was made for.
+    pub stage: usize,
+    /// Upgrades taken so far this run.
+    pub taken: Vec<RunUpgrade>,
+    /// What is on offer while the run is in `RunState::ChoosingUpgrade`.
+    pub offered: Vec<RunUpgrade>,
+    /// Stages reached that are still owed an offer, oldest first.
+    pub pending: Vec<usize>,
+}
+
+impl RunUpgrades {
+    pub fn times_taken(&self, upgrade: RunUpgrade) -> usize {
+        self.taken.iter().filter(|taken| **taken == upgrade).count()
+    }
+
+    /// Draws up to `UPGRADE_CHOICES` different upgrades that are not maxed out,
+    /// weighted by rarity.
+    pub fn roll_offer(&mut self, rng: &mut GameRng) {
+        let mut candidates: Vec<&RunUpgradeDef> = RUN_UPGRADE_POOL
+            .iter()
+            .filter(|def| self.times_taken(def.upgrade) < def.max_picks)
+            .collect();
+        self.offered.clear();
+        while self.offered.len() < UPGRADE_CHOICES && !candidates.is_empty() {
+            let weights = WeightedIndex::new(candidates.iter().map(|def| def.rarity.weight()))
+                .expect("rarity weights are positive");
+            let def = candidates.remove(weights.sample(&mut **rng));
+            self.offered.push(def.upgrade);
+        }
+    }
+}
+
+#[derive(Component)]
+struct UpgradeChoiceMenu;
+
+fn text_style(ui_assets: &UiAssets) -> TextStyle {
+    TextStyle {
+        font: ui_assets.menu_font.clone(),
+        font_size: 40.0,
+        color: Color::rgb(0.8, 0.8, 0.8),
+    }
+}
+
+pub fn offer_run_upgrades(
+    mut event_reader: EventReader<StageReached>,
+    mut run_upgrades: ResMut<RunUpgrades>,
+    mut rng: ResMut<GameRng>,
+    mut next_run_state: ResMut<NextState<RunState>>,
+) {
+    run_upgrades
+        .pending
+        .extend(event_reader.iter().map(|event| event.stage));
+    if run_upgrades.pending.is_empty() {
+        return;
+    }
+    // One offer at a time. The rest follow as soon as the run resumes.
+    run_upgrades.stage = run_upgrades.pending.remove(0);
+    run_upgrades.roll_offer(&mut rng);
+    if run_upgrades.offered.is_empty() {
+        run_upgrades.pending.clear();
+    } else {
+        next_run_state.set(RunState::ChoosingUpgrade);
+    }
+}
+
+/// Keys 1 to 3 take an offer. The autopilot always takes the first one.
+pub fn choose_run_upgrade(
+    keyboard_input: Res<Input<KeyCode>>,
+    autopilot: Res<Autopilot>,
+    mut run_upgrades: ResMut<RunUpgrades>,
+    mut player_query: Query<&mut Player>,
+    mut gun_stats: ResMut<GunStats>,
+    mut bullet_cooldown_timer: ResMut<BulletCooldownTimer>,
+    mut magnet: ResMut<Magnet>,
+    mut next_run_state: ResMut<NextState<RunState>>,
+) {
+    let choice = if autopilot.enabled {
+        Some(0)
+    } else {
+        [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3]
+            .iter()
+            .position(|key| keyboard_input.just_pressed(*key))
+    };
+    let Some(&upgrade) = choice.and_then(|index| run_upgrades.offered.get(index)) else {
+        return;
+    };
+    if let Ok(mut player) = player_query.get_single_mut() {
+        upgrade.apply(
+            &mut player,
+            &mut gun_stats,
+            &mut bullet_cooldown_timer,
+            &mut magnet,
+        );
+    }
+    run_upgrades.taken.push(upgrade);
+    run_upgrades.offered.clear();
+    next_run_state.set(RunState::Playing);
+}
+
+fn spawn_upgrade_choice_menu(
+    mut commands: Commands,
+    ui_assets: Res<UiAssets>,
+    run_upgrades: Option<Res<RunUpgrades>>,
+) {
+    let Some(run_upgrades) = run_upgrades else {
+        return;
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+            UpgradeChoiceMenu {},
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!("Stage {} reached", run_upgrades.stage),
+                TextStyle {
+                    font_size: 80.0,
+                    color: Color::rgb(0.9, 0.9, 1.0),
+                    ..text_style(&ui_assets)
+                },
+            ));
+            for (index, upgrade) in run_upgrades.offered.iter().enumerate() {
+                let def = upgrade.def();
+                parent.spawn(TextBundle::from_section(
+                    format!(
+                        "{}  {} ({}): {}",
+                        index + 1,
+                        def.name,
+                        def.rarity.name(),
+                        def.description
+                    ),
+                    TextStyle {
+                        color: def.rarity.color(),
+                        ..text_style(&ui_assets)
+                    },
+                ));
+            }
+            parent.spawn(TextBundle::from_section(
+                "Press 1, 2 or 3 to choose",
+                TextStyle {
+                    font_size: 30.0,
+                    ..text_style(&ui_assets)
+                },
+            ));
+        });
+}
+
+fn despawn_upgrade_choice_menu(
+    mut commands: Commands,
+    menu_query: Query<Entity, With<UpgradeChoiceMenu>>,
+) {
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn resume_run(mut next_run_state: ResMut<NextState<RunState>>) {
+    next_run_state.set(RunState::Playing);
+}
+
+pub fn add_run_upgrades_resource(mut commands: Commands) {
+    commands.insert_resource(RunUpgrades::default())
+}
+
+pub fn remove_run_upgrades_resource(mut commands: Commands) {
+    commands.remove_resource::<RunUpgrades>();
+}
+
+pub struct RunUpgradePlugin;
+
+impl Plugin for RunUpgradePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(add_run_upgrades_resource.in_schedule(OnEnter(AppState::Game)))
+            .add_system(
+                offer_run_upgrades
+                    .after(set_difficulty)
+                    .after(spawn_mineral_drops)
+                    .in_set(GameSet::Effects)
+                    .in_set(OnUpdate(AppState::Game)),
+            )
+            .add_system(
+                choose_run_upgrade
+                    .run_if(in_state(AppState::Game))
+                    .in_set(OnUpdate(RunState::ChoosingUpgrade)),
+            )
+            .add_system(spawn_upgrade_choice_menu.in_schedule(OnEnter(RunState::ChoosingUpgrade)))
+            .add_system(despawn_upgrade_choice_menu.in_schedule(OnExit(RunState::ChoosingUpgrade)))
+            .add_systems(
+                (remove_run_upgrades_resource, resume_run).in_schedule(OnExit(AppState::Game)),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        headless::{enter_game, headless_app},
+        rock::{Rock, RockSize, RockSpeed, RocksDestroyed},
+    };
+    use bevy::input::{keyboard::KeyboardInput, ButtonState};
+
+    fn run_state(app: &App) -> RunState {
+        app.world.resource::<State<RunState>>().0.clone()
+    }
+
+    #[test]
+    fn offers_never_repeat_or_exceed_max_picks() {
+        let mut rng = GameRng::seeded(7);
+        let mut run_upgrades = RunUpgrades::default();
+        for _ in 0..20 {
+            run_upgrades.roll_offer(&mut rng);
+            let mut offered = run_upgrades.offered.clone();
+            offered.dedup();
+            assert_eq!(offered.len(), run_upgrades.offered.len());
+            if let Some(&upgrade) = run_upgrades.offered.first() {
+                run_upgrades.taken.push(upgrade);
+            }
+        }
+
+        for def in RUN_UPGRADE_POOL.iter() {
+            assert_eq!(run_upgrades.times_taken(def.upgrade), def.max_picks);
+        }
+        assert!(run_upgrades.offered.is_empty());
+    }
+
+    #[test]
+    fn reaching_a_stage_pauses_until_an_upgrade_is_chosen() {
+        let mut app = headless_app();
+        app.insert_resource(GameRng::seeded(3));
+        enter_game(&mut app);
+        let rock = app
+            .world
+            .spawn((
+                Transform::from_xyz(400.0, 600.0, 0.0),
+                Rock {
+                    rock_size: RockSize::Small,
+                    rock_speed: RockSpeed::Slow,
+                },
+            ))
+            .id();
+        app.world.resource_mut::<RocksDestroyed>().count = 11;
+
+        app.update();
+        app.update();
+        assert_eq!(run_state(&app), RunState::ChoosingUpgrade);
+        let offered = app.world.resource::<RunUpgrades>().offered.clone();
+        assert_eq!(offered.len(), UPGRADE_CHOICES);
+
+        let paused_at = app.world.get::<Transform>(rock).unwrap().translation;
+        app.update();
+        assert_eq!(
+            app.world.get::<Transform>(rock).unwrap().translation,
+            paused_at
+        );
+
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::Key2),
+            state: ButtonState::Pressed,
+        });
+        app.update();
+        app.update();
+
+        assert_eq!(run_state(&app), RunState::Playing);
+        assert_eq!(app.world.resource::<RunUpgrades>().taken, vec![offered[1]]);
+        assert!(app.world.get::<Transform>(rock).unwrap().translation.y < paused_at.y);
+    }
+
+    #[test]
+    fn crossing_several_stages_at_once_queues_an_offer_for_each() {
+        let mut app = headless_app();
+        app.insert_resource(GameRng::seeded(3));
+        enter_game(&mut app);
+        app.world.resource_mut::<RocksDestroyed>().count = 30;
+
+        let mut offered_stages = Vec::new();
+        for _ in 0..10 {
+            app.update();
+            if run_state(&app) == RunState::ChoosingUpgrade {
+                offered_stages.push(app.world.resource::<RunUpgrades>().stage);
+                app.world.send_event(KeyboardInput {
+                    scan_code: 0,
+                    key_code: Some(KeyCode::Key1),
+                    state: ButtonState::Pressed,
+                });
+                app.update();
+                app.world.send_event(KeyboardInput {
+                    scan_code: 0,
+                    key_code: Some(KeyCode::Key1),
+                    state: ButtonState::Released,
+                });
+            }
+        }
+
+        assert_eq!(offered_stages, vec![1, 2]);
+        assert_eq!(app.world.resource::<RunUpgrades>().taken.len(), 2);
+        assert_eq!(app.world.resource::<RunUpgrades>().stage, 2);
+    }
+}
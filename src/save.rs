@@ -0,0 +1,87 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::shop::UpgradeLevels;
+
+pub const SAVE_FILE: &str = "save.ron";
+/// How many of the best scores the leaderboard keeps.
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// Progress that carries over between runs.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SaveData {
+    /// Banked minerals, spent in the shop.
+    pub wallet: u32,
+    pub upgrades: UpgradeLevels,
+    /// Best scores of past runs, highest first.
+    pub best_scores: Vec<u32>,
+}
+
+/// Where `SaveData` is written. Headless apps leave the path unset so tests
+/// never touch the disk.
+#[derive(Resource, Default)]
+pub struct SaveFile {
+    pub path: Option<PathBuf>,
+}
+
+/// Reads a RON file, falling back to the default if it is missing or unreadable.
+pub fn load_ron<T: DeserializeOwned + Default>(path: &str) -> T {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return T::default();
+    };
+    ron::from_str(&contents).unwrap_or_else(|error| {
+        warn!("ignoring unreadable file {}: {}", path, error);
+        T::default()
+    })
+}
+
+pub fn write_ron<T: Serialize>(value: &T, path: &Path) {
+    let result = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|error| error.to_string())
+        .and_then(|contents| fs::write(path, contents).map_err(|error| error.to_string()));
+    if let Err(error) = result {
+        error!("could not write {}: {}", path.display(), error);
+    }
+}
+
+impl SaveData {
+    /// Falls back to a fresh save if the file is missing or unreadable.
+    pub fn load(path: &str) -> SaveData {
+        load_ron(path)
+    }
+
+    /// Adds a finished run to the leaderboard if it beats one of the best.
+    pub fn record_score(&mut self, score: u32) {
+        let rank = self.best_scores.partition_point(|best| *best >= score);
+        if rank < LEADERBOARD_SIZE {
+            self.best_scores.insert(rank, score);
+            self.best_scores.truncate(LEADERBOARD_SIZE);
+        }
+    }
+}
+
+pub fn write_save_file(save_data: Res<SaveData>, save_file: Res<SaveFile>) {
+    if let Some(path) = &save_file.path {
+        write_ron(&*save_data, path);
+    }
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveData>()
+            .init_resource::<SaveFile>()
+            .add_system(
+                write_save_file
+                    .run_if(resource_changed::<SaveData>())
+                    .in_base_set(CoreSet::Last),
+            );
+    }
+}
@@ -1,29 +1,38 @@
 use bevy::prelude::*;
 
-use crate::state::AppState;
+use crate::{
+    autopilot::Autopilot,
+    save::SaveData,
+    state::{AppState, GameSet},
+};
 
 pub const SCORE_COOLDOWN: f32 = 1.0;
 
 #[derive(Resource)]
 pub struct Score {
     pub value: u32,
+    /// Applied to every score gain, set by the score x2 effect.
+    pub multiplier: u32,
 }
 
 impl Default for Score {
-    fn default() -> Score {
-        Score { value: 0 }
+    fn default() -> Self {
+        Score {
+            value: 0,
+            multiplier: 1,
+        }
     }
 }
 
-#[derive(Resource)]
-pub struct Highscore {
-    pub value: u32,
+impl Score {
+    pub fn add(&mut self, points: u32) {
+        self.value += points * self.multiplier;
+    }
 }
 
-impl Default for Highscore {
-    fn default() -> Highscore {
-        Highscore { value: 0 }
-    }
+#[derive(Resource, Default)]
+pub struct Highscore {
+    pub value: u32,
 }
 
 #[derive(Resource)]
@@ -41,7 +50,7 @@ impl Default for ScoreTimer {
 
 pub fn add_score_over_time(mut score: ResMut<Score>, score_timer: Res<ScoreTimer>) {
     if score_timer.timer.finished() {
-        score.value += 5;
+        score.add(5);
     }
 }
 
@@ -65,12 +74,26 @@ pub fn remove_score_timer_resource(mut commands: Commands) {
     commands.remove_resource::<ScoreTimer>();
 }
 
-pub fn update_highscore(score: Res<Score>, mut highscore: ResMut<Highscore>) {
-    if score.value > highscore.value {
+pub fn update_highscore(
+    score: Res<Score>,
+    mut highscore: ResMut<Highscore>,
+    autopilot: Res<Autopilot>,
+) {
+    if score.value > highscore.value && !autopilot.attract_mode {
         highscore.value = score.value;
     }
 }
 
+pub fn record_leaderboard_score(
+    score: Res<Score>,
+    mut save_data: ResMut<SaveData>,
+    autopilot: Res<Autopilot>,
+) {
+    if score.value > 0 && !autopilot.attract_mode {
+        save_data.record_score(score.value);
+    }
+}
+
 pub struct ScorePlugin;
 
 impl Plugin for ScorePlugin {
@@ -79,12 +102,19 @@ impl Plugin for ScorePlugin {
             .add_systems(
                 (add_score_resource, add_score_timer_resource).in_schedule(OnEnter(AppState::Game)),
             )
-            .add_systems((add_score_over_time, tick_score_timer).in_set(OnUpdate(AppState::Game)))
+            .add_systems(
+                (
+                    tick_score_timer.in_set(GameSet::Timers),
+                    add_score_over_time.in_set(GameSet::Spawn),
+                )
+                    .in_set(OnUpdate(AppState::Game)),
+            )
             .add_systems(
                 (
                     remove_score_resource,
                     remove_score_timer_resource,
                     update_highscore,
+                    record_leaderboard_score,
                 )
                     .in_schedule(OnExit(AppState::Game)),
             );
@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use bevy::{
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::{apply_arena_layout, Arena, ArenaLayout},
+    camera_shake::ScreenShakeSettings,
+    rock::Difficulty,
+    save::{load_ron, write_ron},
+    sound::Volumes,
+};
+
+pub const SETTINGS_FILE: &str = "settings.ron";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    BorderlessFullscreen,
+    Fullscreen,
+}
+
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 3] = [
+        DisplayMode::Windowed,
+        DisplayMode::BorderlessFullscreen,
+        DisplayMode::Fullscreen,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisplayMode::Windowed => "Windowed",
+            DisplayMode::BorderlessFullscreen => "Borderless",
+            DisplayMode::Fullscreen => "Fullscreen",
+        }
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            DisplayMode::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+/// Player preferences, kept apart from `SaveData` so that wiping progress
+/// does not reset them.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub display_mode: DisplayMode,
+    pub vsync: bool,
+    /// Scales the arena only by whole multiples of a sprite pixel.
+    pub pixel_perfect: bool,
+    pub volumes: Volumes,
+    pub screen_shake: ScreenShakeSettings,
+    /// Difficulty new runs start at.
+    pub difficulty: Difficulty,
+    pub arena: ArenaLayout,
+}
+
+impl Settings {
+    /// Falls back to the defaults if the file is missing or unreadable.
+    pub fn load(path: &str) -> Settings {
+        load_ron(path)
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+}
+
+/// Where `Settings` are written. Headless apps leave the path unset.
+#[derive(Resource, Default)]
+pub struct SettingsFile {
+    pub path: Option<PathBuf>,
+}
+
+pub fn write_settings_file(settings: Res<Settings>, settings_file: Res<SettingsFile>) {
+    if let Some(path) = &settings_file.path {
+        write_ron(&*settings, path);
+    }
+}
+
+pub fn apply_window_settings(
+    settings: Res<Settings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    for mut window in window_query.iter_mut() {
+        let mode = settings.display_mode.window_mode();
+        if window.mode != mode {
+            window.mode = mode;
+        }
+        let present_mode = settings.present_mode();
+        if window.present_mode != present_mode {
+            window.present_mode = present_mode;
+        }
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .init_resource::<SettingsFile>()
+            .init_resource::<Arena>()
+            .add_system(apply_arena_layout.run_if(resource_changed::<Settings>()))
+            .add_system(apply_window_settings.run_if(resource_changed::<Settings>()))
+            .add_system(
+                write_settings_file
+                    .run_if(resource_changed::<Settings>())
+                    .in_base_set(CoreSet::Last),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        headless::{enter_game, headless_app},
+        rock::RockSpawnTimer,
+    };
+
+    #[test]
+    fn missing_fields_keep_their_defaults() {
+        let settings: Settings = ron::from_str("(vsync: true, difficulty: Hard)").unwrap();
+        assert!(settings.vsync);
+        assert_eq!(settings.difficulty, Difficulty::Hard);
+        assert_eq!(settings.display_mode, DisplayMode::Windowed);
+        assert_eq!(settings.volumes, Volumes::default());
+        assert!(settings.screen_shake.shake);
+    }
+
+    #[test]
+    fn runs_start_at_the_chosen_difficulty() {
+        let mut app = headless_app();
+        app.world.resource_mut::<Settings>().difficulty = Difficulty::Easy;
+        enter_game(&mut app);
+        let easy = app.world.resource::<RockSpawnTimer>().timer.duration();
+
+        let mut app = headless_app();
+        app.world.resource_mut::<Settings>().difficulty = Difficulty::Hard;
+        enter_game(&mut app);
+        let hard = app.world.resource::<RockSpawnTimer>().timer.duration();
+        assert!(hard < easy);
+    }
+}
@@ -0,0 +1,390 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::UiAssets,
+    gun::BULLET_COOLDOWN,
+    player::{PLAYER_SPEED, PLAYER_STARTING_BOMBS, PLAYER_STARTING_HEALTH},
+    save::SaveData,
+    space_crates::{CrateType, DEFAULT_CRATE_WEIGHTS},
+    state::AppState,
+    widgets::{
+        reset_menu_focus, spawn_button, ButtonActivated, MenuInput, MenuWidgetSet, BUTTON_WIDTH,
+    },
+};
+
+/// Permanent upgrades bought in the hangar with banked minerals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Upgrade {
+    MaxHealth,
+    FireRate,
+    Speed,
+    StartingBombs,
+    CrateOdds,
+}
+
+impl Upgrade {
+    pub const ALL: [Upgrade; 5] = [
+        Upgrade::MaxHealth,
+        Upgrade::FireRate,
+        Upgrade::Speed,
+        Upgrade::StartingBombs,
+        Upgrade::CrateOdds,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Upgrade::MaxHealth => "Hull plating",
+            Upgrade::FireRate => "Gun cooling",
+            Upgrade::Speed => "Thrusters",
+            Upgrade::StartingBombs => "Bomb rack",
+            Upgrade::CrateOdds => "Cargo scanner",
+        }
+    }
+
+    pub fn max_level(&self) -> u32 {
+        match self {
+            Upgrade::StartingBombs => 3,
+            _ => 5,
+        }
+    }
+
+    /// Minerals needed to buy the next level after `level`.
+    pub fn cost(&self, level: u32) -> u32 {
+        let base = match self {
+            Upgrade::MaxHealth => 20,
+            Upgrade::FireRate => 30,
+            Upgrade::Speed => 20,
+            Upgrade::StartingBombs => 40,
+            Upgrade::CrateOdds => 25,
+        };
+        base * (level + 1)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct UpgradeLevels {
+    pub max_health: u32,
+    pub fire_rate: u32,
+    pub speed: u32,
+    pub starting_bombs: u32,
+    pub crate_odds: u32,
+}
+
+impl UpgradeLevels {
+    pub fn level(&self, upgrade: Upgrade) -> u32 {
+        match upgrade {
+            Upgrade::MaxHealth => self.max_health,
+            Upgrade::FireRate => self.fire_rate,
+            Upgrade::Speed => self.speed,
+            Upgrade::StartingBombs => self.starting_bombs,
+            Upgrade::CrateOdds => self.crate_odds,
+        }
+    }
+
+    fn level_mut(&mut self, upgrade: Upgrade) -> &mut u32 {
+        match upgrade {
+            Upgrade::MaxHealth => &mut self.max_health,
+            Upgrade::FireRate => &mut self.fire_rate,
+            Upgrade::Speed => &mut self.speed,
+            Upgrade::StartingBombs => &mut self.starting_bombs,
+            Upgrade::CrateOdds => &mut self.crate_odds,
+        }
+    }
+
+    pub fn max_health(&self) -> f32 {
+        PLAYER_STARTING_HEALTH + 20.0 * self.max_health as f32
+    }
+
+    pub fn bullet_cooldown(&self) -> f32 {
+        BULLET_COOLDOWN * 0.85_f32.powi(self.fire_rate as i32)
+    }
+
+    pub fn player_speed(&self) -> f32 {
+        PLAYER_SPEED * (1.0 + 0.1 * self.speed as f32)
+    }
+
+    pub fn starting_bombs(&self) -> u32 {
+        PLAYER_STARTING_BOMBS + self.starting_bombs
+    }
+
+    /// Explosive crates get rarer with every level of the cargo scanner.
+    pub fn crate_weights(&self) -> Vec<(CrateType, u32)> {
+        DEFAULT_CRATE_WEIGHTS
+            .iter()
+            .map(|&(crate_type, weight)| match crate_type {
+                CrateType::Explosive => (crate_type, weight * 5 / (5 + 2 * self.crate_odds)),
+                _ => (crate_type, weight),
+            })
+            .collect()
+    }
+}
+
+impl SaveData {
+    /// Whether the next level of `upgrade` is for sale and affordable.
+    pub fn can_buy(&self, upgrade: Upgrade) -> bool {
+        let level = self.upgrades.level(upgrade);
+        level < upgrade.max_level() && self.wallet >= upgrade.cost(level)
+    }
+
+    /// Spends minerals on the next level of `upgrade`. Returns `false` if it
+    /// is maxed out or the wallet is short.
+    pub fn buy(&mut self, upgrade: Upgrade) -> bool {
+        if !self.can_buy(upgrade) {
+            return false;
+        }
+        self.wallet -= upgrade.cost(self.upgrades.level(upgrade));
+        *self.upgrades.level_mut(upgrade) += 1;
+        true
+    }
+}
+
+#[derive(Component)]
+struct ShopMenu;
+
+#[derive(Component)]
+struct ShopWalletText;
+
+#[derive(Component)]
+struct ShopButton {
+    upgrade: Upgrade,
+}
+
+#[derive(Component)]
+struct ShopBackButton;
+
+/// The level and price next to an upgrade's button.
+#[derive(Component)]
+struct ShopPriceText {
+    upgrade: Upgrade,
+}
+
+fn price_text(upgrade: Upgrade, save_data: &SaveData) -> String {
+    let level = save_data.upgrades.level(upgrade);
+    let price = if level >= upgrade.max_level() {
+        "maxed".to_string()
+    } else {
+        format!("{} minerals", upgrade.cost(level))
+    };
+    format!("{}/{}  {}", level, upgrade.max_level(), price)
+}
+
+fn text_style(ui_assets: &UiAssets) -> TextStyle {
+    TextStyle {
+        font: ui_assets.menu_font.clone(),
+        font_size: 50.0,
+        color: Color::rgb(0.8, 0.8, 0.8),
+    }
+}
+
+fn spawn_shop_menu(mut commands: Commands, ui_assets: Res<UiAssets>, save_data: Res<SaveData>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            ShopMenu {},
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Hangar",
+                TextStyle {
+                    font_size: 100.0,
+                    color: Color::rgb(0.9, 0.9, 1.0),
+                    ..text_style(&ui_assets)
+                },
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    format!("Minerals: {}", save_data.wallet),
+                    text_style(&ui_assets),
+                ),
+                ShopWalletText {},
+            ));
+            for (order, upgrade) in Upgrade::ALL.into_iter().enumerate() {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(BUTTON_WIDTH + 300.0), Val::Auto),
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::SpaceBetween,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        spawn_button(
+                            parent,
+                            &ui_assets,
+                            upgrade.name(),
+                            order,
+                            ShopButton { upgrade },
+                        );
+                        parent.spawn((
+                            TextBundle::from_section(
+                                price_text(upgrade, &save_data),
+                                text_style(&ui_assets),
+                            ),
+                            ShopPriceText { upgrade },
+                        ));
+                    });
+            }
+            spawn_button(
+                parent,
+                &ui_assets,
+                "Back",
+                Upgrade::ALL.len(),
+                ShopBackButton,
+            );
+            parent.spawn(TextBundle::from_section(
+                "Enter to buy, Escape to leave",
+                TextStyle {
+                    font_size: 30.0,
+                    ..text_style(&ui_assets)
+                },
+            ));
+        });
+}
+
+fn despawn_shop_menu(mut commands: Commands, menu_query: Query<Entity, With<ShopMenu>>) {
+    if let Ok(menu) = menu_query.get_single() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+fn shop_input(
+    menu_input: Res<MenuInput>,
+    mut activated_event_reader: EventReader<ButtonActivated>,
+    button_query: Query<&ShopButton>,
+    back_query: Query<(), With<ShopBackButton>>,
+    mut save_data: ResMut<SaveData>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    for activated in activated_event_reader.iter() {
+        if back_query.contains(activated.entity) {
+            next_app_state.set(AppState::MainMenu);
+        }
+        let Ok(button) = button_query.get(activated.entity) else {
+            continue;
+        };
+        // Only borrow mutably for a real purchase, so refused ones don't rewrite the save file.
+        if save_data.can_buy(button.upgrade) {
+            save_data.buy(button.upgrade);
+        }
+    }
+    if menu_input.back {
+        next_app_state.set(AppState::MainMenu);
+    }
+}
+
+fn update_shop_text(
+    mut price_query: Query<(&mut Text, &ShopPriceText), Without<ShopWalletText>>,
+    mut wallet_query: Query<&mut Text, With<ShopWalletText>>,
+    save_data: Res<SaveData>,
+) {
+    if !save_data.is_changed() {
+        return;
+    }
+    for (mut text, price) in price_query.iter_mut() {
+        text.sections[0].value = price_text(price.upgrade, &save_data);
+    }
+    for mut text in wallet_query.iter_mut() {
+        text.sections[0].value = format!("Minerals: {}", save_data.wallet);
+    }
+}
+
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems((spawn_shop_menu, reset_menu_focus).in_schedule(OnEnter(AppState::Shop)))
+            .add_systems(
+                (
+                    shop_input.after(MenuWidgetSet),
+                    update_shop_text.after(shop_input),
+                )
+                    .in_set(OnUpdate(AppState::Shop)),
+            )
+            .add_system(despawn_shop_menu.in_schedule(OnExit(AppState::Shop)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gun::BulletCooldownTimer,
+        headless::{enter_game, headless_app},
+        player::Player,
+    };
+
+    #[test]
+    fn buying_spends_minerals_until_maxed_or_broke() {
+        let mut save_data = SaveData {
+            wallet: 130,
+            ..default()
+        };
+
+        assert!(save_data.buy(Upgrade::StartingBombs));
+        assert!(save_data.buy(Upgrade::StartingBombs));
+        assert!(!save_data.buy(Upgrade::StartingBombs));
+        assert_eq!(save_data.wallet, 10);
+        assert_eq!(save_data.upgrades.starting_bombs, 2);
+
+        save_data.wallet = 1000;
+        assert!(save_data.buy(Upgrade::StartingBombs));
+        assert!(!save_data.buy(Upgrade::StartingBombs));
+        assert_eq!(save_data.upgrades.starting_bombs, 3);
+    }
+
+    #[test]
+    fn refused_purchases_leave_the_save_unchanged() {
+        let mut world = World::new();
+        world.insert_resource(SaveData::default());
+        world.init_resource::<MenuInput>();
+        world.init_resource::<Events<ButtonActivated>>();
+        world.init_resource::<NextState<AppState>>();
+        let button = world
+            .spawn(ShopButton {
+                upgrade: Upgrade::MaxHealth,
+            })
+            .id();
+        world.send_event(ButtonActivated { entity: button });
+        let mut system = IntoSystem::into_system(shop_input);
+        system.initialize(&mut world);
+        world.clear_trackers();
+
+        system.run((), &mut world);
+
+        assert!(!world.is_resource_changed::<SaveData>());
+        assert_eq!(world.resource::<SaveData>().upgrades.max_health, 0);
+    }
+
+    #[test]
+    fn bought_upgrades_apply_to_the_next_run() {
+        let mut app = headless_app();
+        {
+            let mut save_data = app.world.resource_mut::<SaveData>();
+            save_data.upgrades.max_health = 2;
+            save_data.upgrades.fire_rate = 1;
+            save_data.upgrades.starting_bombs = 1;
+        }
+
+        enter_game(&mut app);
+
+        let player = app.world.query::<&Player>().single(&app.world);
+        assert!(player.max_health > PLAYER_STARTING_HEALTH);
+        assert_eq!(player.health, player.max_health);
+        assert_eq!(player.bombs, 2);
+        assert!(app.world.resource::<BulletCooldownTimer>().cooldown < 1.0);
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use bevy::{audio::Source, prelude::*};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{arena::Arena, assets::AudioAssets, player::Player, settings::Settings};
+
+/// Assumed length of a sound that has not finished loading yet.
+pub const UNLOADED_SOUND_SECONDS: f32 = 0.5;
+pub const VOLUME_STEP: f32 = 0.1;
+/// How far sounds at the arena edges are placed toward each ear, where the
+/// ears are 1 to either side of the listener.
+pub const PAN_WIDTH: f32 = 0.8;
+/// Volume lost by a sound on the far side of the arena from the player.
+pub const DISTANCE_ATTENUATION: f32 = 0.3;
+/// A sound right between the ears reaches each of them at this volume.
+const CENTRE_GAIN: f32 = 0.75;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    RockHit,
+    CollectRepair,
+    HitExplosive,
+    Shoot,
+    CollectShield,
+    CollectRapidFire,
+    CollectScoreBonus,
+    CollectMagnet,
+    CollectSlowMotion,
+    CollectBomb,
+    CollectDoubleDamage,
+    CollectSpeedBoost,
+    CollectDoubleScore,
+    CollectMineral,
+}
+
+impl SoundEffect {
+    /// How many copies of the sound may play at once. Further plays are
+    /// dropped, so a shower of rock hits does not add up to a roar.
+    fn max_voices(&self) -> usize {
+        match self {
+            SoundEffect::RockHit | SoundEffect::Shoot | SoundEffect::CollectMineral => 4,
+            SoundEffect::HitExplosive => 3,
+            _ => 2,
+        }
+    }
+
+    /// How far each play may be sped up or slowed down, which shifts its
+    /// pitch so sounds heard over and over do not grate.
+    fn pitch_variance(&self) -> f32 {
+        match self {
+            SoundEffect::RockHit => 0.15,
+            SoundEffect::HitExplosive | SoundEffect::CollectMineral => 0.1,
+            SoundEffect::Shoot => 0.05,
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VolumeChannel {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl VolumeChannel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VolumeChannel::Master => "Master",
+            VolumeChannel::Music => "Music",
+            VolumeChannel::Sfx => "Effects",
+        }
+    }
+}
+
+/// Volume of each mixer channel, from 0 to 1.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Volumes {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for Volumes {
+    fn default() -> Self {
+        Volumes {
+            master: 1.0,
+            music: 1.0,
+            sfx: 1.0,
+        }
+    }
+}
+
+impl Volumes {
+    pub fn get(&self, channel: VolumeChannel) -> f32 {
+        match channel {
+            VolumeChannel::Master => self.master,
+            VolumeChannel::Music => self.music,
+            VolumeChannel::Sfx => self.sfx,
+        }
+    }
+
+    /// Moves a channel by whole `VOLUME_STEP`s, staying within 0 and 1.
+    pub fn step(&mut self, channel: VolumeChannel, steps: i32) {
+        let volume = match channel {
+            VolumeChannel::Master => &mut self.master,
+            VolumeChannel::Music => &mut self.music,
+            VolumeChannel::Sfx => &mut self.sfx,
+        };
+        let step = (*volume / VOLUME_STEP).round() as i32 + steps;
+        *volume = (step as f32 * VOLUME_STEP).clamp(0.0, 1.0);
+    }
+
+    /// What a sound on `channel` is played at, with the master volume applied.
+    pub fn output(&self, channel: VolumeChannel) -> f32 {
+        match channel {
+            VolumeChannel::Master => self.master,
+            _ => self.master * self.get(channel),
+        }
+    }
+}
+
+/// Gameplay systems send this instead of touching `Audio` directly, so the
+/// game still runs when no audio output is registered.
+pub struct PlaySound {
+    pub effect: SoundEffect,
+    /// Where in the arena the sound comes from, `None` for sounds that
+    /// come from everywhere at once and play centred.
+    pub pos: Option<Vec2>,
+}
+
+/// Left to right position of a sound between -1 and 1.
+fn pan(pos: Vec2, arena: &Arena) -> f32 {
+    (pos.x / arena.width * 2.0 - 1.0).clamp(-1.0, 1.0)
+}
+
+/// Sounds far from the player are a little quieter.
+fn attenuation(pos: Vec2, listener: Vec2, arena: &Arena) -> f32 {
+    let arena_diagonal = arena.size().length();
+    1.0 - DISTANCE_ATTENUATION * (pos.distance(listener) / arena_diagonal).min(1.0)
+}
+
+impl AudioAssets {
+    fn handle(&self, effect: SoundEffect) -> Handle<AudioSource> {
+        match effect {
+            SoundEffect::RockHit => self.rock_collison.clone(),
+            SoundEffect::CollectRepair => self.collect_repair.clone(),
+            SoundEffect::HitExplosive => self.hit_explosive.clone(),
+            SoundEffect::Shoot => self.shoot.clone(),
+            SoundEffect::CollectShield => self.collect_shield.clone(),
+            SoundEffect::CollectRapidFire => self.collect_rapid_fire.clone(),
+            SoundEffect::CollectScoreBonus => self.collect_score.clone(),
+            SoundEffect::CollectMagnet => self.collect_magnet.clone(),
+            SoundEffect::CollectSlowMotion => self.collect_slow_motion.clone(),
+            SoundEffect::CollectBomb => self.collect_bomb.clone(),
+            SoundEffect::CollectDoubleDamage => self.collect_double_damage.clone(),
+            SoundEffect::CollectSpeedBoost => self.collect_speed_boost.clone(),
+            SoundEffect::CollectDoubleScore => self.collect_double_score.clone(),
+            SoundEffect::CollectMineral => self.collect_mineral.clone(),
+        }
+    }
+}
+
+/// Sound effects still playing, to hold each one to its `max_voices`.
+#[derive(Resource, Default)]
+pub struct SoundVoices {
+    /// Each voice and the real time it ends at.
+    playing: Vec<(SoundEffect, f64)>,
+    lengths: HashMap<SoundEffect, f32>,
+}
+
+/// Sinks cannot tell when they run dry, so the length of a sound is worked
+/// out once by decoding it.
+fn sound_length(source: &AudioSource) -> f32 {
+    let decoder = source.decoder();
+    let samples_per_second = decoder.channels() as f32 * decoder.sample_rate() as f32;
+    decoder.count() as f32 / samples_per_second
+}
+
+fn play_sounds(
+    mut event_reader: EventReader<PlaySound>,
+    mut voices: ResMut<SoundVoices>,
+    audio: Res<Audio>,
+    audio_sources: Res<Assets<AudioSource>>,
+    handles: Res<AudioAssets>,
+    settings: Res<Settings>,
+    player_query: Query<&Transform, With<Player>>,
+    arena: Res<Arena>,
+    time: Res<Time>,
+) {
+    let listener = player_query
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+    let now = time.raw_elapsed_seconds_f64();
+    voices.playing.retain(|(_, end)| *end > now);
+    let volume = settings.volumes.output(VolumeChannel::Sfx);
+    let mut rng = thread_rng();
+    for event in event_reader.iter() {
+        let effect = event.effect;
+        let voice_count = voices
+            .playing
+            .iter()
+            .filter(|(playing, _)| *playing == effect)
+            .count();
+        if voice_count >= effect.max_voices() {
+            continue;
+        }
+        let handle = handles.handle(effect);
+        let length = match voices.lengths.get(&effect) {
+            Some(length) => *length,
+            None => match audio_sources.get(&handle) {
+                Some(source) => *voices.lengths.entry(effect).or_insert(sound_length(source)),
+                None => UNLOADED_SOUND_SECONDS,
+            },
+        };
+        let speed = 1.0 + effect.pitch_variance() * rng.gen_range(-1.0..=1.0);
+        let settings = PlaybackSettings::ONCE.with_speed(speed);
+        match event.pos {
+            Some(pos) => {
+                let volume =
+                    volume * listener.map_or(1.0, |listener| attenuation(pos, listener, &arena));
+                // Spatial playback turns down the ear further from the
+                // sound, and both ears a little even for centred sounds.
+                audio.play_spatial_with_settings(
+                    handle,
+                    settings.with_volume(volume / CENTRE_GAIN),
+                    Transform::IDENTITY,
+                    2.0,
+                    Vec3::new(pan(pos, &arena) * PAN_WIDTH, 0.0, 0.0),
+                );
+            }
+            None => {
+                audio.play_with_settings(handle, settings.with_volume(volume));
+            }
+        }
+        voices.playing.push((effect, now + (length / speed) as f64));
+    }
+}
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SoundVoices>().add_system(play_sounds);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_steps_stay_on_the_grid_and_within_range() {
+        let mut volumes = Volumes::default();
+        volumes.step(VolumeChannel::Music, 3);
+        assert_eq!(volumes.music, 1.0);
+        for _ in 0..7 {
+            volumes.step(VolumeChannel::Music, -1);
+        }
+        assert_eq!(volumes.music, 0.3);
+        volumes.step(VolumeChannel::Music, -10);
+        assert_eq!(volumes.music, 0.0);
+
+        volumes.step(VolumeChannel::Master, -5);
+        volumes.step(VolumeChannel::Music, 8);
+        assert_eq!(volumes.output(VolumeChannel::Master), 0.5);
+        assert!((volumes.output(VolumeChannel::Music) - 0.4).abs() < 1e-6);
+        assert_eq!(volumes.output(VolumeChannel::Sfx), 0.5);
+    }
+
+    #[test]
+    fn sounds_pan_across_the_arena_and_fade_a_little_with_distance() {
+        let arena = Arena::default();
+        assert_eq!(pan(Vec2::new(0.0, 300.0), &arena), -1.0);
+        assert_eq!(pan(Vec2::new(arena.width / 2.0, 0.0), &arena), 0.0);
+        assert_eq!(pan(Vec2::new(arena.width + 50.0, 0.0), &arena), 1.0);
+
+        let corner = arena.size();
+        assert_eq!(attenuation(corner, corner, &arena), 1.0);
+        assert!(
+            (attenuation(Vec2::ZERO, corner, &arena) - (1.0 - DISTANCE_ATTENUATION)).abs() < 1e-6
+        );
+    }
+}
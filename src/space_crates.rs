@@ -1,10 +1,18 @@
 use bevy::prelude::*;
-use rand::prelude::*;
+use rand::{distributions::WeightedIndex, prelude::*};
 
 use crate::{
-    arena::{ARENA_HEIGHT, ARENA_WIDTH},
+    arena::Arena,
     assets::SpriteAssets,
-    state::AppState,
+    destruction::PendingDestruction,
+    effects::{ActiveEffects, EffectKind},
+    magnet::{attract_magnetic, Magnet, Magnetic},
+    minerals::collect_minerals,
+    rng::GameRng,
+    rock::spawn_rocks_over_time,
+    save::SaveData,
+    sound::SoundEffect,
+    state::{AppState, GameSet},
     ASSET_SCALE,
 };
 
@@ -14,10 +22,125 @@ pub const CRATE_HEIGHT: f32 = 16.0 * ASSET_SCALE;
 pub const CRATE_COOLDOWN: f32 = 10.0;
 pub const CRATE_HEAL: f32 = 20.0;
 pub const CRATE_DAMAGE: f32 = 75.0;
+/// Camera trauma of picking up an explosive crate.
+pub const CRATE_TRAUMA: f32 = 0.7;
+pub const CRATE_SCORE_BONUS: u32 = 500;
 
+/// Relative odds of each crate type being spawned.
+pub const DEFAULT_CRATE_WEIGHTS: [(CrateType, u32); 11] = [
+    (CrateType::Health, 30),
+    (CrateType::Explosive, 25),
+    (CrateType::ShieldRecharge, 10),
+    (CrateType::RapidFire, 10),
+    (CrateType::ScoreBonus, 10),
+    (CrateType::Magnet, 6),
+    (CrateType::SlowMotion, 5),
+    (CrateType::ExtraBomb, 4),
+    (CrateType::DoubleDamage, 6),
+    (CrateType::SpeedBoost, 6),
+    (CrateType::DoubleScore, 5),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CrateType {
     Health,
     Explosive,
+    ShieldRecharge,
+    RapidFire,
+    ScoreBonus,
+    Magnet,
+    SlowMotion,
+    ExtraBomb,
+    DoubleDamage,
+    SpeedBoost,
+    DoubleScore,
+}
+
+impl CrateType {
+    pub fn sprite(&self, handles: &SpriteAssets) -> Handle<Image> {
+        match self {
+            CrateType::Health => handles.health_crate.clone(),
+            CrateType::Explosive => handles.explosive_crate.clone(),
+            CrateType::ShieldRecharge => handles.shield_crate.clone(),
+            CrateType::RapidFire => handles.rapid_fire_crate.clone(),
+            CrateType::ScoreBonus => handles.score_crate.clone(),
+            CrateType::Magnet => handles.magnet_crate.clone(),
+            CrateType::SlowMotion => handles.slow_motion_crate.clone(),
+            CrateType::ExtraBomb => handles.bomb_crate.clone(),
+            CrateType::DoubleDamage => handles.double_damage_crate.clone(),
+            CrateType::SpeedBoost => handles.speed_boost_crate.clone(),
+            CrateType::DoubleScore => handles.double_score_crate.clone(),
+        }
+    }
+
+    pub fn pickup_sound(&self) -> SoundEffect {
+        match self {
+            CrateType::Health => SoundEffect::CollectRepair,
+            CrateType::Explosive => SoundEffect::HitExplosive,
+            CrateType::ShieldRecharge => SoundEffect::CollectShield,
+            CrateType::RapidFire => SoundEffect::CollectRapidFire,
+            CrateType::ScoreBonus => SoundEffect::CollectScoreBonus,
+            CrateType::Magnet => SoundEffect::CollectMagnet,
+            CrateType::SlowMotion => SoundEffect::CollectSlowMotion,
+            CrateType::ExtraBomb => SoundEffect::CollectBomb,
+            CrateType::DoubleDamage => SoundEffect::CollectDoubleDamage,
+            CrateType::SpeedBoost => SoundEffect::CollectSpeedBoost,
+            CrateType::DoubleScore => SoundEffect::CollectDoubleScore,
+        }
+    }
+
+    /// Whether the magnet pulls this crate. Hazards are left to fall so the
+    /// magnet never drags them into the ship.
+    pub fn magnetic(&self) -> bool {
+        !matches!(self, CrateType::Explosive)
+    }
+
+    /// The timed effect collecting this crate starts, if any.
+    pub fn effect(&self) -> Option<EffectKind> {
+        match self {
+            CrateType::RapidFire => Some(EffectKind::RapidFire),
+            CrateType::Magnet => Some(EffectKind::Magnet),
+            CrateType::SlowMotion => Some(EffectKind::SlowMotion),
+            CrateType::DoubleDamage => Some(EffectKind::DoubleDamage),
+            CrateType::SpeedBoost => Some(EffectKind::SpeedBoost),
+            CrateType::DoubleScore => Some(EffectKind::DoubleScore),
+            _ => None,
+        }
+    }
+}
+
+/// Weighted table `spawn_crates` rolls on. Change the weights to change the odds.
+#[derive(Resource)]
+pub struct CrateLootTable {
+    entries: Vec<(CrateType, u32)>,
+    /// `None` when no entry has a positive weight.
+    weights: Option<WeightedIndex<u32>>,
+}
+
+impl Default for CrateLootTable {
+    fn default() -> Self {
+        CrateLootTable::new(DEFAULT_CRATE_WEIGHTS.to_vec())
+    }
+}
+
+impl CrateLootTable {
+    pub fn new(entries: Vec<(CrateType, u32)>) -> Self {
+        let weights = WeightedIndex::new(entries.iter().map(|(_, weight)| *weight)).ok();
+        CrateLootTable { entries, weights }
+    }
+
+    /// Picks a crate type, or `None` if the table can't drop anything.
+    pub fn roll<R: Rng>(&self, rng: &mut R) -> Option<CrateType> {
+        let weights = self.weights.as_ref()?;
+        Some(self.entries[weights.sample(rng)].0)
+    }
+}
+
+/// Sent when the player collects a crate. Each effect has its own system
+/// in `pickups` listening for the crate types it cares about.
+pub struct CratePickup {
+    pub crate_type: CrateType,
+    pub pos: Vec3,
 }
 
 #[derive(Component)]
@@ -42,47 +165,67 @@ pub fn spawn_crates(
     mut commands: Commands,
     handles: Res<SpriteAssets>,
     crate_spawn_timer: Res<CrateSpawnTimer>,
+    loot_table: Res<CrateLootTable>,
+    mut rng: ResMut<GameRng>,
+    arena: Res<Arena>,
 ) {
     if crate_spawn_timer.timer.finished() {
-        let random_x = random::<f32>() * ARENA_WIDTH;
+        let random_x = rng.gen::<f32>() * arena.width;
 
-        let mut rng = thread_rng();
-        let (crate_type, crate_sprite) = match rng.gen_range(0..2) {
-            0 => (CrateType::Health, handles.health_crate.clone()),
-            _ => (CrateType::Explosive, handles.explosive_crate.clone()),
+        let Some(crate_type) = loot_table.roll(&mut **rng) else {
+            return;
         };
-        commands.spawn((
+        let mut entity = commands.spawn((
             SpriteBundle {
-                transform: Transform::from_xyz(random_x, CRATE_HEIGHT + ARENA_HEIGHT, 0.0)
+                transform: Transform::from_xyz(random_x, CRATE_HEIGHT + arena.height, 0.0)
                     .with_scale(Vec3 {
                         x: ASSET_SCALE,
                         y: ASSET_SCALE,
                         ..default()
                     }),
-                texture: crate_sprite,
+                texture: crate_type.sprite(&handles),
                 ..default()
             },
             SpaceCrate { crate_type },
         ));
+        if crate_type.magnetic() {
+            entity.insert(Magnetic {});
+        }
     }
 }
 
-pub fn tick_crate_spawn_timer(mut crate_spawn_timer: ResMut<CrateSpawnTimer>, time: Res<Time>) {
-    crate_spawn_timer.timer.tick(time.delta());
+pub fn tick_crate_spawn_timer(
+    mut crate_spawn_timer: ResMut<CrateSpawnTimer>,
+    active_effects: Res<ActiveEffects>,
+    time: Res<Time>,
+) {
+    crate_spawn_timer
+        .timer
+        .tick(time.delta().mul_f32(active_effects.world_speed()));
 }
 
-pub fn move_crates(mut crate_query: Query<&mut Transform, With<SpaceCrate>>, time: Res<Time>) {
-    for mut transform in crate_query.iter_mut() {
-        transform.translation.y -= CRATE_SPEED * time.delta_seconds();
+pub fn move_crates(
+    mut crate_query: Query<(&mut Transform, Option<&Magnetic>), With<SpaceCrate>>,
+    active_effects: Res<ActiveEffects>,
+    magnet: Res<Magnet>,
+    time: Res<Time>,
+) {
+    for (mut transform, magnetic) in crate_query.iter_mut() {
+        if magnetic.is_some() && magnet.pulls(transform.translation.truncate()) {
+            continue;
+        }
+        transform.translation.y -=
+            CRATE_SPEED * active_effects.world_speed() * time.delta_seconds();
     }
 }
 
 pub fn remove_off_screen_crates(
     mut commands: Commands,
     crate_query: Query<(Entity, &Transform), With<SpaceCrate>>,
+    mut pending_destruction: ResMut<PendingDestruction>,
 ) {
     for (entity, transform) in crate_query.iter() {
-        if transform.translation.y < 0.0 - CRATE_HEIGHT {
+        if transform.translation.y < 0.0 - CRATE_HEIGHT && pending_destruction.mark(entity) {
             commands.entity(entity).despawn();
         }
     }
@@ -102,17 +245,34 @@ pub fn remove_crate_timer_resource(mut commands: Commands) {
     commands.remove_resource::<CrateSpawnTimer>();
 }
 
+/// Rebuilds the loot table at the start of a run so the cargo scanner
+/// upgrade takes effect.
+pub fn apply_crate_odds_upgrade(mut loot_table: ResMut<CrateLootTable>, save_data: Res<SaveData>) {
+    *loot_table = CrateLootTable::new(save_data.upgrades.crate_weights());
+}
+
 pub struct CratePlugin;
 
 impl Plugin for CratePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(add_crate_timer_resource.in_schedule(OnEnter(AppState::Game)))
+        app.init_resource::<CrateLootTable>()
+            .add_event::<CratePickup>()
+            .add_systems(
+                (add_crate_timer_resource, apply_crate_odds_upgrade)
+                    .in_schedule(OnEnter(AppState::Game)),
+            )
             .add_systems(
                 (
-                    spawn_crates,
-                    tick_crate_spawn_timer,
-                    move_crates,
-                    remove_off_screen_crates,
+                    tick_crate_spawn_timer.in_set(GameSet::Timers),
+                    spawn_crates
+                        .after(spawn_rocks_over_time)
+                        .in_set(GameSet::Spawn),
+                    move_crates
+                        .after(attract_magnetic)
+                        .in_set(GameSet::Movement),
+                    remove_off_screen_crates
+                        .after(collect_minerals)
+                        .in_set(GameSet::Collision),
                 )
                     .in_set(OnUpdate(AppState::Game)),
             )
@@ -121,3 +281,21 @@ impl Plugin for CratePlugin {
             );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loot_tables_without_positive_weights_drop_nothing() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(CrateLootTable::new(Vec::new()).roll(&mut rng), None);
+        let all_zero = CrateLootTable::new(vec![(CrateType::Health, 0), (CrateType::Magnet, 0)]);
+        assert_eq!(all_zero.roll(&mut rng), None);
+        let only_magnets =
+            CrateLootTable::new(vec![(CrateType::Health, 0), (CrateType::Magnet, 3)]);
+        for _ in 0..10 {
+            assert_eq!(only_magnets.roll(&mut rng), Some(CrateType::Magnet));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+pub const SPATIAL_HASH_CELL_SIZE: f32 = 100.0;
+
+/// Uniform grid broadphase. Entities are bucketed into every cell their
+/// bounding square overlaps, positions outside the grid are clamped into
+/// the border cells so things spawning above the arena are still found.
+#[derive(Resource)]
+pub struct SpatialHash {
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<Entity>>,
+}
+
+impl SpatialHash {
+    pub fn new(width: f32, height: f32, cell_size: f32) -> Self {
+        let columns = (width / cell_size).ceil().max(1.0) as usize;
+        let rows = (height / cell_size).ceil().max(1.0) as usize;
+        SpatialHash {
+            cell_size,
+            columns,
+            rows,
+            cells: vec![Vec::new(); columns * rows],
+        }
+    }
+
+    /// Empties every cell but keeps their allocations for the next rebuild.
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2, half_extent: f32) {
+        let (min_column, min_row, max_column, max_row) = self.cell_range(position, half_extent);
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                self.cells[row * self.columns + column].push(entity);
+            }
+        }
+    }
+
+    /// Fills `out` with every entity sharing a cell with the given square.
+    /// Candidates still need a narrow phase check.
+    pub fn query(&self, position: Vec2, half_extent: f32, out: &mut Vec<Entity>) {
+        out.clear();
+        let (min_column, min_row, max_column, max_row) = self.cell_range(position, half_extent);
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                out.extend_from_slice(&self.cells[row * self.columns + column]);
+            }
+        }
+        if min_column != max_column || min_row != max_row {
+            out.sort_unstable();
+            out.dedup();
+        }
+    }
+
+    fn cell_range(&self, position: Vec2, half_extent: f32) -> (usize, usize, usize, usize) {
+        (
+            self.column(position.x - half_extent),
+            self.row(position.y - half_extent),
+            self.column(position.x + half_extent),
+            self.row(position.y + half_extent),
+        )
+    }
+
+    fn column(&self, x: f32) -> usize {
+        ((x / self.cell_size).floor().max(0.0) as usize).min(self.columns - 1)
+    }
+
+    fn row(&self, y: f32) -> usize {
+        ((y / self.cell_size).floor().max(0.0) as usize).min(self.rows - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_spanning_several_cells_are_found_once() {
+        let mut hash = SpatialHash::new(400.0, 400.0, 100.0);
+        let rock = Entity::from_raw(1);
+        hash.insert(rock, Vec2::new(100.0, 100.0), 30.0);
+        let mut found = Vec::new();
+
+        hash.query(Vec2::new(100.0, 100.0), 60.0, &mut found);
+        assert_eq!(found, vec![rock]);
+        hash.query(Vec2::new(80.0, 120.0), 5.0, &mut found);
+        assert_eq!(found, vec![rock]);
+        hash.query(Vec2::new(300.0, 300.0), 5.0, &mut found);
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn positions_at_and_beyond_the_edges_land_in_the_border_cells() {
+        let mut hash = SpatialHash::new(400.0, 400.0, 100.0);
+        let above = Entity::from_raw(1);
+        let at_corner = Entity::from_raw(2);
+        let below = Entity::from_raw(3);
+        hash.insert(above, Vec2::new(250.0, 480.0), 10.0);
+        hash.insert(at_corner, Vec2::new(400.0, 400.0), 0.0);
+        hash.insert(below, Vec2::new(-50.0, -900.0), 10.0);
+        let mut found = Vec::new();
+
+        hash.query(Vec2::new(250.0, 395.0), 1.0, &mut found);
+        assert_eq!(found, vec![above]);
+        hash.query(Vec2::new(399.0, 399.0), 0.0, &mut found);
+        assert_eq!(found, vec![at_corner]);
+        hash.query(Vec2::new(5.0, 5.0), 1.0, &mut found);
+        assert_eq!(found, vec![below]);
+        hash.query(Vec2::new(-1000.0, -1000.0), 0.0, &mut found);
+        assert_eq!(found, vec![below]);
+    }
+
+    #[test]
+    fn clearing_removes_entities_and_reinserting_moves_them() {
+        let mut hash = SpatialHash::new(400.0, 400.0, 100.0);
+        let rock = Entity::from_raw(1);
+        hash.insert(rock, Vec2::new(50.0, 50.0), 10.0);
+        let mut found = Vec::new();
+
+        hash.clear();
+        hash.query(Vec2::new(50.0, 50.0), 10.0, &mut found);
+        assert!(found.is_empty());
+
+        hash.insert(rock, Vec2::new(350.0, 50.0), 10.0);
+        hash.query(Vec2::new(50.0, 50.0), 10.0, &mut found);
+        assert!(found.is_empty());
+        hash.query(Vec2::new(350.0, 50.0), 10.0, &mut found);
+        assert_eq!(found, vec![rock]);
+    }
+}
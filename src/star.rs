@@ -1,55 +1,206 @@
 use crate::{
-    arena::{ARENA_HEIGHT, ARENA_WIDTH},
-    ASSET_SCALE,
+    arena::Arena, assets::SpriteAssets, effects::ActiveEffects, player::Player,
+    rock::DifficultyStage, ASSET_SCALE,
 };
 use bevy::prelude::*;
 use rand::prelude::*;
 
-pub const STAR_COUNT: u32 = 100;
-pub const STAR_SPEED: f32 = 40.0;
+/// Stars of a layer this fast drift `PLAYER_PARALLAX` times as far as the
+/// player moves, faster layers drift further and slower ones less.
+pub const STAR_BASE_SPEED: f32 = 40.0;
+pub const PLAYER_PARALLAX: f32 = 0.05;
+/// Extra scroll speed for every difficulty stage reached.
+pub const STAGE_SPEED_STEP: f32 = 0.2;
+/// How quickly the scroll speed eases toward its target, per second.
+pub const SCROLL_EASING: f32 = 2.0;
+pub const TWINKLE_SPEED: f32 = 3.0;
+/// Distance past the arena edge before a star wraps around.
+pub const STAR_MARGIN: f32 = 10.0;
+
+/// One depth of the starfield. Far layers are small, dim and slow.
+#[derive(Clone, Debug)]
+pub struct StarLayer {
+    pub count: u32,
+    pub speed: f32,
+    pub size: f32,
+    pub brightness: f32,
+    /// How much of the brightness comes and goes, 0 for steady stars.
+    pub twinkle: f32,
+}
+
+/// The layers of the starfield, from the farthest to the nearest.
+#[derive(Resource)]
+pub struct StarField {
+    pub layers: Vec<StarLayer>,
+}
+
+impl Default for StarField {
+    fn default() -> Self {
+        StarField {
+            layers: vec![
+                StarLayer {
+                    count: 120,
+                    speed: 15.0,
+                    size: 0.5,
+                    brightness: 0.35,
+                    twinkle: 0.6,
+                },
+                StarLayer {
+                    count: 60,
+                    speed: 40.0,
+                    size: 0.75,
+                    brightness: 0.6,
+                    twinkle: 0.3,
+                },
+                StarLayer {
+                    count: 25,
+                    speed: 90.0,
+                    size: 1.0,
+                    brightness: 1.0,
+                    twinkle: 0.1,
+                },
+            ],
+        }
+    }
+}
+
+/// Multiplier on every layer's speed, eased toward boosts and difficulty.
+#[derive(Resource)]
+pub struct StarScroll {
+    pub factor: f32,
+}
+
+impl Default for StarScroll {
+    fn default() -> Self {
+        StarScroll { factor: 1.0 }
+    }
+}
 
 #[derive(Component)]
-pub struct Star {}
-
-pub fn spawn_stars(mut commands: Commands, asset_server: Res<AssetServer>) {
-    for _ in 0..STAR_COUNT {
-        let random_x = random::<f32>() * ARENA_WIDTH;
-        let random_y = random::<f32>() * ARENA_HEIGHT;
-
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform {
-                    translation: Vec3 {
-                        x: random_x,
-                        y: random_y,
-                        z: -1.0,
-                    },
-                    scale: Vec3 {
-                        x: ASSET_SCALE,
-                        y: ASSET_SCALE,
+pub struct Star {
+    layer: usize,
+    twinkle_phase: f32,
+}
+
+/// Scatters the stars over the arena, again whenever it changes shape.
+pub fn spawn_stars(
+    mut commands: Commands,
+    star_query: Query<Entity, With<Star>>,
+    star_field: Res<StarField>,
+    sprite_assets: Res<SpriteAssets>,
+    arena: Res<Arena>,
+) {
+    for entity in star_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let mut rng = thread_rng();
+    let layer_count = star_field.layers.len();
+    for (index, layer) in star_field.layers.iter().enumerate() {
+        for _ in 0..layer.count {
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform {
+                        translation: Vec3::new(
+                            rng.gen::<f32>() * arena.width,
+                            rng.gen::<f32>() * arena.height,
+                            // Behind the bullets at -1, with nearer layers
+                            // drawn over farther ones.
+                            -1.0 - (layer_count - index) as f32 * 0.1,
+                        ),
+                        scale: Vec3::new(layer.size * ASSET_SCALE, layer.size * ASSET_SCALE, 1.0),
                         ..default()
                     },
+                    texture: sprite_assets.star.clone(),
                     ..default()
                 },
-                texture: asset_server.load("sprites/star.png"),
-                ..default()
-            },
-            Star {},
-        ));
+                Star {
+                    layer: index,
+                    twinkle_phase: rng.gen::<f32>() * std::f32::consts::TAU,
+                },
+            ));
+        }
     }
 }
 
-pub fn move_stars(mut star_query: Query<&mut Transform, With<Star>>, time: Res<Time>) {
-    for mut transform in star_query.iter_mut() {
-        transform.translation.y -= STAR_SPEED * time.delta_seconds();
+pub fn update_star_scroll(
+    mut star_scroll: ResMut<StarScroll>,
+    active_effects: Option<Res<ActiveEffects>>,
+    difficulty_stage: Option<Res<DifficultyStage>>,
+    time: Res<Time>,
+) {
+    let boost = active_effects.map_or(1.0, |effects| effects.player_speed());
+    let stage = difficulty_stage.map_or(0, |stage| stage.stage);
+    let target = boost * (1.0 + stage as f32 * STAGE_SPEED_STEP);
+    let easing = (SCROLL_EASING * time.delta_seconds()).min(1.0);
+    star_scroll.factor += (target - star_scroll.factor) * easing;
+}
+
+/// How far the player moved, taking the short way around when the ship
+/// wrapped from one edge of the arena to the other.
+fn player_movement(last: Vec2, pos: Vec2, arena: &Arena) -> Vec2 {
+    let unwrap = |delta: f32, size: f32| (delta + size / 2.0).rem_euclid(size) - size / 2.0;
+    let delta = pos - last;
+    Vec2::new(unwrap(delta.x, arena.width), unwrap(delta.y, arena.height))
+}
+
+pub fn move_stars(
+    mut star_query: Query<(&mut Transform, &Star), Without<Player>>,
+    player_query: Query<&Transform, With<Player>>,
+    mut last_player_pos: Local<Option<Vec2>>,
+    star_field: Res<StarField>,
+    star_scroll: Res<StarScroll>,
+    arena: Res<Arena>,
+    time: Res<Time>,
+) {
+    let player_pos = player_query
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+    let player_delta = match (*last_player_pos, player_pos) {
+        (Some(last), Some(pos)) => player_movement(last, pos, &arena),
+        _ => Vec2::ZERO,
+    };
+    *last_player_pos = player_pos;
+
+    for (mut transform, star) in star_query.iter_mut() {
+        let speed = star_field.layers[star.layer].speed;
+        let parallax = player_delta * PLAYER_PARALLAX * speed / STAR_BASE_SPEED;
+        transform.translation.x -= parallax.x;
+        transform.translation.y -= speed * star_scroll.factor * time.delta_seconds() + parallax.y;
     }
 }
 
-pub fn send_star_to_top(mut star_query: Query<&mut Transform, With<Star>>) {
+pub fn wrap_stars(mut star_query: Query<&mut Transform, With<Star>>, arena: Res<Arena>) {
     for mut transform in star_query.iter_mut() {
-        if transform.translation.y < -10.0 {
-            transform.translation.y = ARENA_HEIGHT + 10.0;
+        let translation = &mut transform.translation;
+        if translation.y < -STAR_MARGIN {
+            translation.y += arena.height + 2.0 * STAR_MARGIN;
+        } else if translation.y > arena.height + STAR_MARGIN {
+            translation.y -= arena.height + 2.0 * STAR_MARGIN;
         }
+        if translation.x < -STAR_MARGIN {
+            translation.x += arena.width + 2.0 * STAR_MARGIN;
+        } else if translation.x > arena.width + STAR_MARGIN {
+            translation.x -= arena.width + 2.0 * STAR_MARGIN;
+        }
+    }
+}
+
+pub fn twinkle_stars(
+    mut star_query: Query<(&mut Sprite, &Star)>,
+    star_field: Res<StarField>,
+    time: Res<Time>,
+) {
+    let t = time.elapsed_seconds() * TWINKLE_SPEED;
+    for (mut sprite, star) in star_query.iter_mut() {
+        let layer = &star_field.layers[star.layer];
+        let flicker = 0.5 + 0.5 * (t + star.twinkle_phase).sin();
+        sprite.color = Color::rgba(
+            1.0,
+            1.0,
+            1.0,
+            layer.brightness * (1.0 - layer.twinkle * flicker),
+        );
     }
 }
 
@@ -57,7 +208,64 @@ pub struct StarPlugin;
 
 impl Plugin for StarPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_stars)
-            .add_systems((move_stars, send_star_to_top));
+        app.init_resource::<StarField>()
+            .init_resource::<StarScroll>()
+            .add_systems((
+                spawn_stars.run_if(resource_changed::<Arena>()),
+                update_star_scroll,
+                move_stars.after(update_star_scroll),
+                wrap_stars.after(move_stars),
+                twinkle_stars,
+            ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::headless_app;
+
+    #[test]
+    fn near_layers_scroll_faster_and_every_layer_is_spawned() {
+        let mut app = headless_app();
+        app.add_plugin(StarPlugin);
+        app.update();
+        let star_field = StarField::default();
+        let total: u32 = star_field.layers.iter().map(|layer| layer.count).sum();
+        let before: Vec<(Entity, f32)> = app
+            .world
+            .query::<(Entity, &Transform, &Star)>()
+            .iter(&app.world)
+            .map(|(entity, transform, _)| (entity, transform.translation.y))
+            .collect();
+        assert_eq!(before.len(), total as usize);
+
+        app.update();
+        let mut moved = vec![0.0; star_field.layers.len()];
+        for (entity, y) in before {
+            let star = app.world.get::<Star>(entity).unwrap();
+            let now = app.world.get::<Transform>(entity).unwrap().translation.y;
+            // Ignore stars that wrapped around this tick.
+            if now < y {
+                moved[star.layer] = y - now;
+            }
+        }
+        assert!(moved[0] > 0.0);
+        assert!(moved[0] < moved[1] && moved[1] < moved[2]);
+    }
+
+    #[test]
+    fn wrapping_across_an_edge_counts_as_a_short_move() {
+        let arena = Arena {
+            width: 900.0,
+            height: 900.0,
+            wrap: true,
+        };
+        let across = player_movement(Vec2::new(898.0, 450.0), Vec2::new(2.0, 450.0), &arena);
+        assert!((across - Vec2::new(4.0, 0.0)).length() < 1e-3);
+        let back = player_movement(Vec2::new(450.0, 1.0), Vec2::new(450.0, 897.0), &arena);
+        assert!((back - Vec2::new(0.0, -4.0)).length() < 1e-3);
+        let step = player_movement(Vec2::new(100.0, 100.0), Vec2::new(103.0, 98.0), &arena);
+        assert!((step - Vec2::new(3.0, -2.0)).length() < 1e-3);
     }
 }
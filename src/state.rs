@@ -5,18 +5,31 @@ pub enum AppState {
     #[default]
     MainMenu,
     Game,
+    /// The hangar, where banked minerals buy upgrades.
+    Shop,
+    Settings,
+    /// The best scores of past runs.
+    Leaderboard,
 }
 
-pub fn start_game(
-    keyboard_input: Res<Input<KeyCode>>,
-    app_state: Res<State<AppState>>,
-    mut next_app_state: ResMut<NextState<AppState>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Return) {
-        if app_state.0 != AppState::Game {
-            next_app_state.set(AppState::Game);
-        }
-    }
+/// Whether the action of a run is playing or on hold while the player picks
+/// an upgrade between difficulty stages.
+#[derive(States, PartialEq, Eq, Debug, Clone, Hash, Default)]
+pub enum RunState {
+    #[default]
+    Playing,
+    ChoosingUpgrade,
+}
+
+/// Phases of a gameplay tick. They always run in this order so that a
+/// seeded run plays out the same way every time.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum GameSet {
+    Timers,
+    Spawn,
+    Movement,
+    Collision,
+    Effects,
 }
 
 pub fn go_to_main_menu(
@@ -24,9 +37,7 @@ pub fn go_to_main_menu(
     app_state: Res<State<AppState>>,
     mut next_app_state: ResMut<NextState<AppState>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Return) {
-        if app_state.0 != AppState::MainMenu {
-            next_app_state.set(AppState::MainMenu);
-        }
+    if keyboard_input.just_pressed(KeyCode::Return) && app_state.0 == AppState::Game {
+        next_app_state.set(AppState::MainMenu);
     }
 }
@@ -1,17 +1,78 @@
-use bevy::prelude::*;
+use bevy::{app::AppExit, prelude::*};
 
 use crate::{
-    assets::UiAssets,
-    player::{Player, UpdatePlayerHealth},
+    arena::{Arena, ArenaLayout},
+    assets::{SpriteAssets, UiAssets, EFFECT_RING_STEPS},
+    effects::{apply_effects, tick_active_effects, ActiveEffects, EffectExpired, EffectKind},
+    minerals::RunMinerals,
+    player::{Player, UpdatePlayerHealth, PLAYER_MAX_SHIELD},
+    rock::Difficulty,
+    save::SaveData,
     score::{Highscore, Score},
+    settings::{DisplayMode, Settings},
+    shop::UpgradeLevels,
+    sound::VolumeChannel,
     state::AppState,
+    widgets::{
+        reset_menu_focus, spawn_button, ButtonActivated, MenuButtonText, MenuFocus, MenuInput,
+        MenuWidgetSet, BUTTON_WIDTH,
+    },
 };
 
 #[derive(Component)]
 struct StartMenu;
 
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+enum MainMenuButton {
+    Play,
+    Settings,
+    Leaderboard,
+    Upgrades,
+    Quit,
+}
+
+impl MainMenuButton {
+    const ALL: [MainMenuButton; 5] = [
+        MainMenuButton::Play,
+        MainMenuButton::Settings,
+        MainMenuButton::Leaderboard,
+        MainMenuButton::Upgrades,
+        MainMenuButton::Quit,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            MainMenuButton::Play => "Play",
+            MainMenuButton::Settings => "Settings",
+            MainMenuButton::Leaderboard => "Leaderboard",
+            MainMenuButton::Upgrades => "Upgrades",
+            MainMenuButton::Quit => "Quit",
+        }
+    }
+}
+
+#[derive(Component)]
+struct LeaderboardMenu;
+
+#[derive(Component)]
+struct BackButton;
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component)]
+struct SettingsButton {
+    item: SettingsItem,
+}
+
+/// The filled part of a volume slider.
+#[derive(Component)]
+struct VolumeFill {
+    channel: VolumeChannel,
+}
+
 #[derive(Component)]
-struct HUD;
+struct Hud;
 
 #[derive(Component)]
 struct ScoreText;
@@ -19,7 +80,92 @@ struct ScoreText;
 #[derive(Component)]
 struct HealthText;
 
-fn spawn_start_menu(mut commands: Commands, ui_assets: Res<UiAssets>, highscore: Res<Highscore>) {
+#[derive(Component)]
+struct MineralText;
+
+/// Animation state of the HUD health bar, as fractions of max health.
+#[derive(Component)]
+struct HealthBar {
+    shown: f32,
+    /// Health recently lost, drained away after a short hold.
+    ghost: f32,
+    ghost_hold: f32,
+    /// 1 right after taking damage, fading to 0.
+    flash: f32,
+}
+
+impl Default for HealthBar {
+    fn default() -> Self {
+        HealthBar {
+            shown: 1.0,
+            ghost: 1.0,
+            ghost_hold: 0.0,
+            flash: 0.0,
+        }
+    }
+}
+
+impl HealthBar {
+    fn track(&mut self, fraction: f32, delta: f32) {
+        self.flash = (self.flash - delta / DAMAGE_FLASH_SECONDS).max(0.0);
+        if fraction < self.shown {
+            self.flash = 1.0;
+            self.ghost_hold = GHOST_HOLD_SECONDS;
+        }
+        self.shown = fraction;
+        if self.ghost_hold > 0.0 {
+            self.ghost_hold -= delta;
+        } else {
+            self.ghost -= GHOST_DRAIN_SPEED * delta;
+        }
+        // Healing moves the ghost up with the bar rather than hiding behind it.
+        self.ghost = self.ghost.max(fraction);
+    }
+}
+
+#[derive(Component)]
+enum HealthFill {
+    Health,
+    Ghost,
+    Shield,
+}
+
+#[derive(Component)]
+struct EffectIcon {
+    kind: EffectKind,
+}
+
+#[derive(Component)]
+struct EffectRing {
+    kind: EffectKind,
+}
+
+const EFFECT_ICON_SIZE: f32 = 48.0;
+const HEALTH_BAR_WIDTH: f32 = 240.0;
+const HEALTH_BAR_HEIGHT: f32 = 20.0;
+const SHIELD_BAR_HEIGHT: f32 = 6.0;
+const HEALTH_PER_SEGMENT: f32 = 10.0;
+/// How long lost health lingers on the bar before draining away.
+const GHOST_HOLD_SECONDS: f32 = 0.4;
+/// Share of the bar the ghost drains per second.
+const GHOST_DRAIN_SPEED: f32 = 0.6;
+const DAMAGE_FLASH_SECONDS: f32 = 0.25;
+const LOW_HEALTH_FRACTION: f32 = 0.25;
+const LOW_HEALTH_PULSE_SPEED: f32 = 8.0;
+const HEALTH_BAR_BACKGROUND: Color = Color::rgba(0.05, 0.05, 0.1, 0.8);
+const HEALTH_COLOR: Color = Color::rgb(0.3, 0.85, 0.4);
+const GHOST_COLOR: Color = Color::rgb(0.9, 0.8, 0.3);
+const LOW_HEALTH_COLOR: Color = Color::rgb(0.85, 0.2, 0.15);
+const LOW_HEALTH_PULSE_COLOR: Color = Color::rgb(1.0, 0.55, 0.45);
+const DAMAGE_FLASH_COLOR: Color = Color::rgb(1.0, 0.1, 0.1);
+const SHIELD_COLOR: Color = Color::rgb(0.35, 0.7, 1.0);
+
+fn spawn_start_menu(
+    mut commands: Commands,
+    ui_assets: Res<UiAssets>,
+    highscore: Res<Highscore>,
+    save_data: Res<SaveData>,
+) {
     commands
         .spawn((
             NodeBundle {
@@ -48,11 +194,16 @@ fn spawn_start_menu(mut commands: Commands, ui_assets: Res<UiAssets>, highscore:
                 ..default()
             });
         })
+        .with_children(|parent| {
+            for (order, button) in MainMenuButton::ALL.into_iter().enumerate() {
+                spawn_button(parent, &ui_assets, button.label(), order, button);
+            }
+        })
         .with_children(|parent| {
             parent.spawn(TextBundle {
                 style: Style { ..default() },
                 text: Text::from_section(
-                    "Press Enter",
+                    format!("Highscore: {}", highscore.value),
                     TextStyle {
                         font: ui_assets.menu_font.clone(),
                         font_size: 50.0,
@@ -66,7 +217,7 @@ fn spawn_start_menu(mut commands: Commands, ui_assets: Res<UiAssets>, highscore:
             parent.spawn(TextBundle {
                 style: Style { ..default() },
                 text: Text::from_section(
-                    format!("Highscore: {}", highscore.value),
+                    format!("Minerals: {}", save_data.wallet),
                     TextStyle {
                         font: ui_assets.menu_font.clone(),
                         font_size: 50.0,
@@ -84,7 +235,178 @@ fn despawn_start_menu(mut commands: Commands, start_menu_query: Query<Entity, Wi
     }
 }
 
-fn hud(mut commands: Commands, ui_assets: Res<UiAssets>) {
+fn main_menu_buttons(
+    mut activated_event_reader: EventReader<ButtonActivated>,
+    button_query: Query<&MainMenuButton>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut exit_event_writer: EventWriter<AppExit>,
+) {
+    for activated in activated_event_reader.iter() {
+        let Ok(button) = button_query.get(activated.entity) else {
+            continue;
+        };
+        match button {
+            MainMenuButton::Play => next_app_state.set(AppState::Game),
+            MainMenuButton::Settings => next_app_state.set(AppState::Settings),
+            MainMenuButton::Leaderboard => next_app_state.set(AppState::Leaderboard),
+            MainMenuButton::Upgrades => next_app_state.set(AppState::Shop),
+            MainMenuButton::Quit => exit_event_writer.send(AppExit),
+        }
+    }
+}
+
+fn spawn_leaderboard_menu(
+    mut commands: Commands,
+    ui_assets: Res<UiAssets>,
+    save_data: Res<SaveData>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            LeaderboardMenu {},
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Leaderboard",
+                TextStyle {
+                    font_size: 100.0,
+                    color: Color::rgb(0.9, 0.9, 1.0),
+                    ..settings_text_style(&ui_assets)
+                },
+            ));
+            if save_data.best_scores.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    "No runs yet",
+                    settings_text_style(&ui_assets),
+                ));
+            }
+            for (rank, score) in save_data.best_scores.iter().enumerate() {
+                parent.spawn(TextBundle::from_section(
+                    format!("{}.  {}", rank + 1, score),
+                    settings_text_style(&ui_assets),
+                ));
+            }
+            spawn_button(parent, &ui_assets, "Back", 0, BackButton);
+        });
+}
+
+fn despawn_leaderboard_menu(
+    mut commands: Commands,
+    menu_query: Query<Entity, With<LeaderboardMenu>>,
+) {
+    if let Ok(menu) = menu_query.get_single() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+fn leaderboard_input(
+    menu_input: Res<MenuInput>,
+    mut activated_event_reader: EventReader<ButtonActivated>,
+    back_query: Query<(), With<BackButton>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    let back_clicked = activated_event_reader
+        .iter()
+        .any(|activated| back_query.contains(activated.entity));
+    if menu_input.back || back_clicked {
+        next_app_state.set(AppState::MainMenu);
+    }
+}
+
+/// One line of the settings screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SettingsItem {
+    DisplayMode,
+    Vsync,
+    PixelPerfect,
+    Volume(VolumeChannel),
+    ScreenShake,
+    HitStop,
+    Difficulty,
+    Arena,
+}
+
+impl SettingsItem {
+    const ALL: [SettingsItem; 10] = [
+        SettingsItem::DisplayMode,
+        SettingsItem::Vsync,
+        SettingsItem::PixelPerfect,
+        SettingsItem::Volume(VolumeChannel::Master),
+        SettingsItem::Volume(VolumeChannel::Music),
+        SettingsItem::Volume(VolumeChannel::Sfx),
+        SettingsItem::ScreenShake,
+        SettingsItem::HitStop,
+        SettingsItem::Difficulty,
+        SettingsItem::Arena,
+    ];
+
+    fn label(&self, settings: &Settings) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        match self {
+            SettingsItem::DisplayMode => format!("Display: {}", settings.display_mode.name()),
+            SettingsItem::Vsync => format!("VSync: {}", on_off(settings.vsync)),
+            SettingsItem::PixelPerfect => {
+                format!("Pixel perfect: {}", on_off(settings.pixel_perfect))
+            }
+            SettingsItem::Volume(channel) => format!("{} volume", channel.name()),
+            SettingsItem::ScreenShake => {
+                format!("Screen shake: {}", on_off(settings.screen_shake.shake))
+            }
+            SettingsItem::HitStop => {
+                format!("Hit stop: {}", on_off(settings.screen_shake.hit_stop))
+            }
+            SettingsItem::Difficulty => format!("Difficulty: {}", settings.difficulty.name()),
+            SettingsItem::Arena => format!("Arena: {}", settings.arena.name()),
+        }
+    }
+
+    /// Left and right cycle through choices, flip toggles and move sliders.
+    fn change(&self, settings: &mut Settings, steps: i32) {
+        match self {
+            SettingsItem::DisplayMode => {
+                settings.display_mode = cycle(&DisplayMode::ALL, settings.display_mode, steps)
+            }
+            SettingsItem::Vsync => settings.vsync = !settings.vsync,
+            SettingsItem::PixelPerfect => settings.pixel_perfect = !settings.pixel_perfect,
+            SettingsItem::Volume(channel) => settings.volumes.step(*channel, steps),
+            SettingsItem::ScreenShake => settings.screen_shake.shake = !settings.screen_shake.shake,
+            SettingsItem::HitStop => {
+                settings.screen_shake.hit_stop = !settings.screen_shake.hit_stop
+            }
+            SettingsItem::Difficulty => {
+                settings.difficulty = cycle(&Difficulty::ALL, settings.difficulty, steps)
+            }
+            SettingsItem::Arena => settings.arena = cycle(&ArenaLayout::ALL, settings.arena, steps),
+        }
+    }
+}
+
+fn cycle<T: Copy + PartialEq>(options: &[T], current: T, steps: i32) -> T {
+    let index = options
+        .iter()
+        .position(|option| *option == current)
+        .unwrap_or(0) as i32;
+    options[(index + steps).rem_euclid(options.len() as i32) as usize]
+}
+
+fn settings_text_style(ui_assets: &UiAssets) -> TextStyle {
+    TextStyle {
+        font: ui_assets.menu_font.clone(),
+        font_size: 40.0,
+        color: Color::rgb(0.8, 0.8, 0.8),
+    }
+}
+
+fn spawn_settings_menu(mut commands: Commands, ui_assets: Res<UiAssets>, settings: Res<Settings>) {
     commands
         .spawn((
             NodeBundle {
@@ -92,18 +414,175 @@ fn hud(mut commands: Commands, ui_assets: Res<UiAssets>) {
                     size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
                     align_items: AlignItems::Center,
                     justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
                     ..default()
                 },
                 ..default()
             },
-            HUD {},
+            SettingsMenu {},
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Settings",
+                TextStyle {
+                    font_size: 80.0,
+                    color: Color::rgb(0.9, 0.9, 1.0),
+                    ..settings_text_style(&ui_assets)
+                },
+            ));
+            for (order, item) in SettingsItem::ALL.into_iter().enumerate() {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(BUTTON_WIDTH + 220.0), Val::Auto),
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::SpaceBetween,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        spawn_button(
+                            parent,
+                            &ui_assets,
+                            &item.label(&settings),
+                            order,
+                            SettingsButton { item },
+                        );
+                        let SettingsItem::Volume(channel) = item else {
+                            return;
+                        };
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(200.0), Val::Px(12.0)),
+                                    ..default()
+                                },
+                                background_color: Color::rgb(0.2, 0.2, 0.25).into(),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            size: Size::new(
+                                                Val::Percent(settings.volumes.get(channel) * 100.0),
+                                                Val::Percent(100.0),
+                                            ),
+                                            ..default()
+                                        },
+                                        background_color: Color::rgb(0.8, 0.8, 0.8).into(),
+                                        ..default()
+                                    },
+                                    VolumeFill { channel },
+                                ));
+                            });
+                    });
+            }
+            spawn_button(
+                parent,
+                &ui_assets,
+                "Back",
+                SettingsItem::ALL.len(),
+                BackButton,
+            );
+            parent.spawn(TextBundle::from_section(
+                "Enter or arrow keys to change, Escape to leave",
+                TextStyle {
+                    font_size: 30.0,
+                    ..settings_text_style(&ui_assets)
+                },
+            ));
+        });
+}
+
+fn despawn_settings_menu(mut commands: Commands, menu_query: Query<Entity, With<SettingsMenu>>) {
+    if let Ok(menu) = menu_query.get_single() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+/// Confirming or clicking a setting steps it forward, left and right step
+/// the focused one. Changes apply as soon as they are made and are written
+/// to the settings file.
+fn settings_input(
+    menu_input: Res<MenuInput>,
+    menu_focus: Res<MenuFocus>,
+    mut activated_event_reader: EventReader<ButtonActivated>,
+    button_query: Query<&SettingsButton>,
+    back_query: Query<(), With<BackButton>>,
+    mut settings: ResMut<Settings>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    for activated in activated_event_reader.iter() {
+        if back_query.contains(activated.entity) {
+            next_app_state.set(AppState::MainMenu);
+        }
+        if let Ok(button) = button_query.get(activated.entity) {
+            button.item.change(&mut settings, 1);
+        }
+    }
+    if let Some(item) = SettingsItem::ALL.get(menu_focus.order) {
+        if menu_input.left {
+            item.change(&mut settings, -1);
+        }
+        if menu_input.right {
+            item.change(&mut settings, 1);
+        }
+    }
+    if menu_input.back {
+        next_app_state.set(AppState::MainMenu);
+    }
+}
+
+fn update_settings_menu(
+    button_query: Query<(&SettingsButton, &Children)>,
+    mut text_query: Query<&mut Text, With<MenuButtonText>>,
+    mut fill_query: Query<(&mut Style, &VolumeFill)>,
+    settings: Res<Settings>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (button, children) in button_query.iter() {
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = button.item.label(&settings);
+        }
+    }
+    for (mut style, fill) in fill_query.iter_mut() {
+        style.size.width = Val::Percent(settings.volumes.get(fill.channel) * 100.0);
+    }
+}
+
+fn hud(
+    mut commands: Commands,
+    ui_assets: Res<UiAssets>,
+    sprite_assets: Res<SpriteAssets>,
+    save_data: Res<SaveData>,
+    arena: Res<Arena>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            Hud {},
         ))
         .with_children(|parent| {
             parent
                 .spawn(NodeBundle {
                     style: Style {
                         justify_content: JustifyContent::SpaceBetween,
-                        size: Size::new(Val::Px(900.0), Val::Px(900.0)),
+                        // The UI is scaled with the camera, so this always covers the arena.
+                        size: Size::new(Val::Px(arena.width), Val::Px(arena.height)),
                         ..default()
                     },
                     ..default()
@@ -115,7 +594,6 @@ fn hud(mut commands: Commands, ui_assets: Res<UiAssets>) {
                                 size: Size {
                                     width: Val::Auto,
                                     height: Val::Percent(5.0),
-                                    ..default()
                                 },
                                 margin: UiRect {
                                     left: Val::Percent(2.),
@@ -141,35 +619,194 @@ fn hud(mut commands: Commands, ui_assets: Res<UiAssets>) {
                                 size: Size {
                                     width: Val::Auto,
                                     height: Val::Percent(5.0),
+                                },
+                                ..default()
+                            },
+                            text: Text::from_section(
+                                "Minerals: 0",
+                                TextStyle {
+                                    font: ui_assets.menu_font.clone(),
+                                    font_size: 50.0,
+                                    color: Color::rgb(0.5, 0.85, 0.9),
+                                },
+                            ),
+                            ..default()
+                        },
+                        MineralText {},
+                    ));
+                    spawn_health_bar(parent, &ui_assets, &save_data.upgrades);
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Percent(2.),
+                                    bottom: Val::Percent(2.),
                                     ..default()
                                 },
-                                margin: UiRect {
-                                    right: Val::Percent(2.),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for kind in EffectKind::ALL {
+                                spawn_effect_icon(parent, kind, &ui_assets, &sprite_assets);
+                            }
+                        });
+                });
+        });
+}
+
+/// A bar split into `HEALTH_PER_SEGMENT` chunks, with the shield as a thin
+/// strip underneath and the rounded numbers below.
+fn spawn_health_bar(parent: &mut ChildBuilder, ui_assets: &UiAssets, upgrades: &UpgradeLevels) {
+    let max_health = upgrades.max_health();
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                margin: UiRect {
+                    top: Val::Px(12.0),
+                    right: Val::Percent(2.),
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(HEALTH_BAR_WIDTH), Val::Px(HEALTH_BAR_HEIGHT)),
+                            ..default()
+                        },
+                        background_color: HEALTH_BAR_BACKGROUND.into(),
+                        ..default()
+                    },
+                    HealthBar::default(),
+                ))
+                .with_children(|parent| {
+                    for (fill, color) in [
+                        (HealthFill::Ghost, GHOST_COLOR),
+                        (HealthFill::Health, HEALTH_COLOR),
+                    ] {
+                        parent.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
                                     ..default()
                                 },
+                                background_color: color.into(),
                                 ..default()
                             },
-                            text: Text {
-                                sections: vec![TextSection::new(
-                                    "Health: 100",
-                                    TextStyle {
-                                        font: ui_assets.menu_font.clone(),
-                                        font_size: 50.0,
-                                        color: Color::rgb(0.8, 0.8, 0.8),
-                                    },
-                                )],
-                                alignment: TextAlignment::Center,
+                            fill,
+                        ));
+                    }
+                    let segments = (max_health / HEALTH_PER_SEGMENT).ceil().max(1.0) as usize;
+                    for segment in 1..segments {
+                        let left = segment as f32 * HEALTH_PER_SEGMENT / max_health * 100.0;
+                        parent.spawn(NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                position: UiRect::left(Val::Percent(left)),
+                                size: Size::new(Val::Px(2.0), Val::Percent(100.0)),
                                 ..default()
                             },
+                            background_color: HEALTH_BAR_BACKGROUND.into(),
+                            ..default()
+                        });
+                    }
+                });
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(HEALTH_BAR_WIDTH), Val::Px(SHIELD_BAR_HEIGHT)),
+                        margin: UiRect::top(Val::Px(2.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: SHIELD_COLOR.into(),
                             ..default()
                         },
-                        HealthText {},
+                        HealthFill::Shield,
                     ));
                 });
+            parent.spawn((
+                TextBundle::from_section(
+                    health_label(max_health, max_health, 0.0, upgrades.starting_bombs()),
+                    TextStyle {
+                        font: ui_assets.menu_font.clone(),
+                        font_size: 32.0,
+                        color: Color::rgb(0.8, 0.8, 0.8),
+                    },
+                ),
+                HealthText {},
+            ));
         });
 }
 
-fn despawn_hud(mut commands: Commands, hud_query: Query<Entity, With<HUD>>) {
+/// A crate icon with a countdown ring around it, hidden until the effect is active.
+fn spawn_effect_icon(
+    parent: &mut ChildBuilder,
+    kind: EffectKind,
+    ui_assets: &UiAssets,
+    sprite_assets: &SpriteAssets,
+) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    size: Size::new(Val::Px(EFFECT_ICON_SIZE), Val::Px(EFFECT_ICON_SIZE)),
+                    margin: UiRect::right(Val::Px(8.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            EffectIcon { kind },
+        ))
+        .with_children(|parent| {
+            parent.spawn(ImageBundle {
+                style: Style {
+                    size: Size::new(
+                        Val::Px(EFFECT_ICON_SIZE * 0.6),
+                        Val::Px(EFFECT_ICON_SIZE * 0.6),
+                    ),
+                    ..default()
+                },
+                image: UiImage::new(kind.icon(sprite_assets)),
+                ..default()
+            });
+            parent.spawn((
+                ImageBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        ..default()
+                    },
+                    image: UiImage::new(ui_assets.effect_rings.last().cloned().unwrap_or_default()),
+                    ..default()
+                },
+                EffectRing { kind },
+            ));
+        });
+}
+
+fn despawn_hud(mut commands: Commands, hud_query: Query<Entity, With<Hud>>) {
     if let Ok(hud) = hud_query.get_single() {
         commands.entity(hud).despawn_recursive();
     }
@@ -178,20 +815,121 @@ fn despawn_hud(mut commands: Commands, hud_query: Query<Entity, With<HUD>>) {
 fn update_score_text(mut text_query: Query<&mut Text, With<ScoreText>>, score: Res<Score>) {
     if score.is_changed() {
         for mut text in text_query.iter_mut() {
-            text.sections[0].value = format!("Score: {}", score.value.to_string());
+            text.sections[0].value = format!("Score: {}", score.value);
         }
     }
 }
 
+fn update_mineral_text(
+    mut text_query: Query<&mut Text, With<MineralText>>,
+    run_minerals: Res<RunMinerals>,
+) {
+    if run_minerals.is_changed() {
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = format!("Minerals: {}", run_minerals.collected);
+        }
+    }
+}
+
+/// Whole numbers only, and never below zero on the tick the player dies.
+/// Health rounds up so a sliver of health still reads as alive.
+fn health_label(health: f32, max_health: f32, shield: f32, bombs: u32) -> String {
+    let mut label = format!(
+        "{}/{}",
+        health.clamp(0.0, max_health).ceil(),
+        max_health.round()
+    );
+    if shield >= 1.0 {
+        label += &format!("  Shield {}", shield.floor());
+    }
+    label + &format!("  Bombs {}", bombs)
+}
+
 fn update_health_text(
     mut text_query: Query<&mut Text, With<HealthText>>,
     player_query: Query<&Player, With<Player>>,
     mut event_reader: EventReader<UpdatePlayerHealth>,
 ) {
+    if event_reader.iter().count() == 0 {
+        return;
+    }
     if let Ok(player) = player_query.get_single() {
-        for _ in event_reader.iter() {
-            for mut text in text_query.iter_mut() {
-                text.sections[0].value = format!("Health: {}", player.health.to_string());
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = health_label(
+                player.health,
+                player.max_health,
+                player.shield,
+                player.bombs,
+            );
+        }
+    }
+}
+
+fn update_health_bar(
+    mut bar_query: Query<&mut HealthBar>,
+    mut fill_query: Query<(&mut Style, &mut BackgroundColor, &HealthFill)>,
+    player_query: Query<&Player>,
+    time: Res<Time>,
+) {
+    let (Ok(mut bar), Ok(player)) = (bar_query.get_single_mut(), player_query.get_single()) else {
+        return;
+    };
+    let fraction = (player.health / player.max_health).clamp(0.0, 1.0);
+    bar.track(fraction, time.delta_seconds());
+
+    let mut color = HEALTH_COLOR;
+    if fraction <= LOW_HEALTH_FRACTION {
+        let pulse = 0.5 + 0.5 * (time.elapsed_seconds() * LOW_HEALTH_PULSE_SPEED).sin();
+        color = lerp_color(LOW_HEALTH_COLOR, LOW_HEALTH_PULSE_COLOR, pulse);
+    }
+    color = lerp_color(color, DAMAGE_FLASH_COLOR, bar.flash);
+
+    for (mut style, mut background, fill) in fill_query.iter_mut() {
+        let width = match fill {
+            HealthFill::Health => {
+                background.0 = color;
+                fraction
+            }
+            HealthFill::Ghost => bar.ghost,
+            HealthFill::Shield => (player.shield / PLAYER_MAX_SHIELD).clamp(0.0, 1.0),
+        };
+        style.size.width = Val::Percent(width * 100.0);
+    }
+}
+
+fn lerp_color(from: Color, to: Color, amount: f32) -> Color {
+    let from = Vec4::from(from.as_rgba_f32());
+    let to = Vec4::from(to.as_rgba_f32());
+    Color::from(from + (to - from) * amount)
+}
+
+fn update_effect_icons(
+    mut icon_query: Query<(&mut Style, &EffectIcon)>,
+    mut ring_query: Query<(&mut UiImage, &EffectRing)>,
+    mut expired_event_reader: EventReader<EffectExpired>,
+    active_effects: Res<ActiveEffects>,
+    ui_assets: Res<UiAssets>,
+) {
+    for (mut style, icon) in icon_query.iter_mut() {
+        if active_effects.is_active(icon.kind) && style.display == Display::None {
+            style.display = Display::Flex;
+        }
+    }
+    for expired in expired_event_reader.iter() {
+        for (mut style, icon) in icon_query.iter_mut() {
+            // An effect picked up again on the frame it ran out stays shown.
+            if icon.kind == expired.kind && !active_effects.is_active(icon.kind) {
+                style.display = Display::None;
+            }
+        }
+    }
+    for (mut image, ring) in ring_query.iter_mut() {
+        if let Some(fraction) = active_effects.remaining_fraction(ring.kind) {
+            let step = (fraction * EFFECT_RING_STEPS as f32).ceil() as usize;
+            if let Some(texture) = ui_assets.effect_rings.get(step) {
+                if image.texture != *texture {
+                    image.texture = texture.clone();
+                }
             }
         }
     }
@@ -204,8 +942,137 @@ impl Plugin for MenuPlugin {
         app.add_system(spawn_start_menu.in_schedule(OnEnter(AppState::MainMenu)))
             .add_system(hud.in_schedule(OnEnter(AppState::Game)))
             .add_system(despawn_start_menu.in_schedule(OnExit(AppState::MainMenu)))
+            .add_system(reset_menu_focus.in_schedule(OnEnter(AppState::MainMenu)))
+            .add_system(
+                main_menu_buttons
+                    .after(MenuWidgetSet)
+                    .in_set(OnUpdate(AppState::MainMenu)),
+            )
+            .add_systems(
+                (spawn_leaderboard_menu, reset_menu_focus)
+                    .in_schedule(OnEnter(AppState::Leaderboard)),
+            )
+            .add_system(
+                leaderboard_input
+                    .after(MenuWidgetSet)
+                    .in_set(OnUpdate(AppState::Leaderboard)),
+            )
+            .add_system(despawn_leaderboard_menu.in_schedule(OnExit(AppState::Leaderboard)))
+            .add_systems(
+                (spawn_settings_menu, reset_menu_focus).in_schedule(OnEnter(AppState::Settings)),
+            )
+            .add_systems(
+                (
+                    settings_input.after(MenuWidgetSet),
+                    update_settings_menu.after(settings_input),
+                )
+                    .in_set(OnUpdate(AppState::Settings)),
+            )
+            .add_system(despawn_settings_menu.in_schedule(OnExit(AppState::Settings)))
             .add_system(update_score_text.in_set(OnUpdate(AppState::Game)))
             .add_system(update_health_text.in_set(OnUpdate(AppState::Game)))
+            .add_system(update_health_bar.in_set(OnUpdate(AppState::Game)))
+            .add_system(update_mineral_text.in_set(OnUpdate(AppState::Game)))
+            .add_system(
+                update_effect_icons
+                    .after(tick_active_effects)
+                    .after(apply_effects)
+                    .in_set(OnUpdate(AppState::Game)),
+            )
             .add_system(despawn_hud.in_schedule(OnExit(AppState::Game)));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        autopilot::Autopilot,
+        headless::{app_state, enter_game, headless_app},
+    };
+    use bevy::input::{keyboard::KeyboardInput, ButtonState};
+
+    fn tap(app: &mut App, key_code: KeyCode) {
+        for state in [ButtonState::Pressed, ButtonState::Released] {
+            app.world.send_event(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key_code),
+                state,
+            });
+            app.update();
+        }
+    }
+
+    #[test]
+    fn health_bar_flashes_and_ghosts_lost_health_and_labels_stay_clamped() {
+        let mut bar = HealthBar::default();
+        bar.track(0.6, 0.1);
+        assert_eq!(bar.flash, 1.0);
+        assert_eq!(bar.ghost, 1.0);
+        for _ in 0..10 {
+            bar.track(0.6, 0.1);
+        }
+        assert_eq!(bar.flash, 0.0);
+        assert!(bar.ghost < 1.0 && bar.ghost >= 0.6);
+        bar.track(0.9, 0.1);
+        assert_eq!(bar.ghost, 0.9);
+
+        assert_eq!(health_label(-7.5, 100.0, 0.0, 1), "0/100  Bombs 1");
+        assert_eq!(
+            health_label(42.3, 100.0, 12.6, 0),
+            "43/100  Shield 12  Bombs 0"
+        );
+    }
+
+    #[test]
+    fn main_menu_buttons_lead_to_the_leaderboard_and_back() {
+        let mut app = headless_app();
+        app.insert_resource(Autopilot {
+            enabled: true,
+            attract_mode: false,
+        });
+        enter_game(&mut app);
+        app.world.resource_mut::<Score>().value = 120;
+        tap(&mut app, KeyCode::Return);
+        assert_eq!(app_state(&app), AppState::MainMenu);
+        assert_eq!(app.world.resource::<SaveData>().best_scores, vec![120]);
+
+        tap(&mut app, KeyCode::Down);
+        tap(&mut app, KeyCode::Down);
+        tap(&mut app, KeyCode::Return);
+        assert_eq!(app_state(&app), AppState::Leaderboard);
+
+        tap(&mut app, KeyCode::Escape);
+        assert_eq!(app_state(&app), AppState::MainMenu);
+        tap(&mut app, KeyCode::Up);
+        tap(&mut app, KeyCode::Return);
+        assert!(!app.world.resource::<Events<AppExit>>().is_empty());
+    }
+
+    #[test]
+    fn settings_rows_are_buttons_that_step_their_setting() {
+        let mut app = headless_app();
+        app.update();
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Settings);
+        app.update();
+        assert_eq!(app_state(&app), AppState::Settings);
+        let display_mode = app.world.resource::<Settings>().display_mode;
+
+        tap(&mut app, KeyCode::Return);
+        let stepped = app.world.resource::<Settings>().display_mode;
+        assert_ne!(stepped, display_mode);
+        tap(&mut app, KeyCode::Left);
+        assert_eq!(app.world.resource::<Settings>().display_mode, display_mode);
+
+        let back = app
+            .world
+            .query_filtered::<Entity, With<BackButton>>()
+            .single(&app.world);
+        app.world.send_event(ButtonActivated { entity: back });
+        app.update();
+        app.update();
+        assert_eq!(app_state(&app), AppState::MainMenu);
+    }
+}
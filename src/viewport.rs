@@ -0,0 +1,98 @@
+use bevy::{prelude::*, render::camera::ScalingMode, window::PrimaryWindow};
+
+use crate::{arena::Arena, settings::Settings, ASSET_SCALE};
+
+/// Physical pixels per world unit that fit the whole arena into the window.
+/// Pixel-perfect scaling rounds down so every sprite texel covers a whole
+/// number of pixels, leaving wider letterbox bars.
+pub fn viewport_scale(physical_size: Vec2, arena: &Arena, pixel_perfect: bool) -> f32 {
+    let fit = (physical_size / arena.size()).min_element();
+    if pixel_perfect {
+        ((fit * ASSET_SCALE).floor() / ASSET_SCALE).max(1.0 / ASSET_SCALE)
+    } else {
+        fit
+    }
+}
+
+/// Zooms the camera so the arena fills as much of the window as it can, and
+/// scales the UI by the same amount so the HUD stays lined up with the arena.
+/// The overflow walls around the arena cover whatever the camera sees past it.
+pub fn fit_camera_to_window(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut projection_query: Query<&mut OrthographicProjection, With<Camera2d>>,
+    mut ui_scale: ResMut<UiScale>,
+    settings: Res<Settings>,
+    arena: Res<Arena>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let physical_size = Vec2::new(
+        window.physical_width() as f32,
+        window.physical_height() as f32,
+    );
+    if physical_size.min_element() <= 0.0 {
+        // Minimised.
+        return;
+    }
+    let logical_scale = viewport_scale(physical_size, &arena, settings.pixel_perfect)
+        / window.scale_factor() as f32;
+
+    for mut projection in projection_query.iter_mut() {
+        if !matches!(projection.scaling_mode, ScalingMode::WindowSize(scale) if scale == logical_scale)
+        {
+            projection.scaling_mode = ScalingMode::WindowSize(logical_scale);
+        }
+    }
+    if ui_scale.scale != logical_scale as f64 {
+        ui_scale.scale = logical_scale as f64;
+    }
+}
+
+/// Camera shake only ever offsets the camera from here, so moving it keeps
+/// the shake intact.
+pub fn center_camera_on_arena(
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+    mut last_center: Local<Vec2>,
+    arena: Res<Arena>,
+) {
+    let center = arena.center();
+    for mut transform in camera_query.iter_mut() {
+        transform.translation += (center - *last_center).extend(0.0);
+    }
+    *last_center = center;
+}
+
+pub struct ViewportPlugin;
+
+impl Plugin for ViewportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                fit_camera_to_window,
+                center_camera_on_arena.run_if(resource_changed::<Arena>()),
+            )
+                .in_base_set(CoreSet::PostUpdate),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_arena_fits_the_short_side_and_pixel_perfect_rounds_down() {
+        let arena = Arena::default();
+        let wide = arena.size() * Vec2::new(3.0, 1.5);
+        assert_eq!(viewport_scale(wide, &arena, false), 1.5);
+        assert_eq!(viewport_scale(wide, &arena, true), 1.5);
+
+        let odd = arena.size() * 1.375;
+        assert_eq!(viewport_scale(odd, &arena, false), 1.375);
+        assert_eq!(viewport_scale(odd, &arena, true), 1.25);
+
+        let tiny = Vec2::new(100.0, 100.0);
+        assert_eq!(viewport_scale(tiny, &arena, true), 1.0 / ASSET_SCALE);
+    }
+}
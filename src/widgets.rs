@@ -0,0 +1,287 @@
+use bevy::prelude::*;
+
+use crate::assets::UiAssets;
+
+pub const BUTTON_WIDTH: f32 = 360.0;
+pub const BUTTON_HEIGHT: f32 = 60.0;
+pub const BUTTON_SPACING: f32 = 8.0;
+const BUTTON_COLOR: Color = Color::rgba(0.1, 0.1, 0.25, 0.6);
+const FOCUSED_BUTTON_COLOR: Color = Color::rgba(0.25, 0.25, 0.6, 0.9);
+const PRESSED_BUTTON_COLOR: Color = Color::rgba(0.45, 0.45, 0.85, 1.0);
+const BUTTON_TEXT_COLOR: Color = Color::rgb(0.6, 0.6, 0.65);
+const FOCUSED_BUTTON_TEXT_COLOR: Color = Color::rgb(1.0, 1.0, 1.0);
+
+/// Menu input from the keyboard and every connected gamepad, read once a
+/// frame so that each screen handles both the same way.
+#[derive(Resource, Default, Debug)]
+pub struct MenuInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub confirm: bool,
+    pub back: bool,
+}
+
+impl MenuInput {
+    pub fn any(&self) -> bool {
+        self.up || self.down || self.left || self.right || self.confirm || self.back
+    }
+}
+
+/// A focusable button. Buttons on screen are focused in `order`, top to bottom.
+#[derive(Component)]
+pub struct MenuButton {
+    pub order: usize,
+}
+
+#[derive(Component)]
+pub struct MenuButtonText;
+
+/// The button that confirm activates. Only one menu is on screen at a time,
+/// so screens share it and reset it when they open.
+#[derive(Resource, Default)]
+pub struct MenuFocus {
+    pub order: usize,
+}
+
+/// Sent when a button is clicked, or confirmed while focused.
+pub struct ButtonActivated {
+    pub entity: Entity,
+}
+
+/// Systems that read menu input and send `ButtonActivated`. Screens handle
+/// their buttons after this set.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct MenuWidgetSet;
+
+/// Spawns a labelled button under `parent`, with `marker` to tell it apart
+/// when it is activated.
+pub fn spawn_button(
+    parent: &mut ChildBuilder,
+    ui_assets: &UiAssets,
+    label: &str,
+    order: usize,
+    marker: impl Bundle,
+) -> Entity {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(BUTTON_WIDTH), Val::Px(BUTTON_HEIGHT)),
+                    margin: UiRect::all(Val::Px(BUTTON_SPACING / 2.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            MenuButton { order },
+            marker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font: ui_assets.menu_font.clone(),
+                        font_size: 44.0,
+                        color: BUTTON_TEXT_COLOR,
+                    },
+                ),
+                MenuButtonText,
+            ));
+        })
+        .id()
+}
+
+pub fn reset_menu_focus(mut menu_focus: ResMut<MenuFocus>) {
+    menu_focus.order = 0;
+}
+
+/// Only the arrow keys and Return navigate, so holding the steering or fire
+/// keys when a menu opens does nothing to it.
+pub fn read_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut menu_input: ResMut<MenuInput>,
+) {
+    let key = |keys: &[KeyCode]| keyboard_input.any_just_pressed(keys.iter().copied());
+    let pad = |button_type: GamepadButtonType| {
+        gamepads
+            .iter()
+            .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+    *menu_input = MenuInput {
+        up: key(&[KeyCode::Up]) || pad(GamepadButtonType::DPadUp),
+        down: key(&[KeyCode::Down]) || pad(GamepadButtonType::DPadDown),
+        left: key(&[KeyCode::Left]) || pad(GamepadButtonType::DPadLeft),
+        right: key(&[KeyCode::Right]) || pad(GamepadButtonType::DPadRight),
+        confirm: key(&[KeyCode::Return]) || pad(GamepadButtonType::South),
+        back: key(&[KeyCode::Escape, KeyCode::Back]) || pad(GamepadButtonType::East),
+    };
+}
+
+/// Up and down move the focus, wrapping around at either end.
+pub fn navigate_menu(
+    menu_input: Res<MenuInput>,
+    button_query: Query<&MenuButton>,
+    mut menu_focus: ResMut<MenuFocus>,
+) {
+    let count = button_query.iter().count();
+    if count == 0 {
+        return;
+    }
+    if menu_input.up {
+        menu_focus.order = (menu_focus.order + count - 1) % count;
+    }
+    if menu_input.down {
+        menu_focus.order = (menu_focus.order + 1) % count;
+    }
+}
+
+/// The mouse focuses whatever it hovers, so mouse and keyboard never
+/// disagree about which button is active.
+pub fn point_at_buttons(
+    interaction_query: Query<(Entity, &Interaction, &MenuButton), Changed<Interaction>>,
+    mut menu_focus: ResMut<MenuFocus>,
+    mut activated_event_writer: EventWriter<ButtonActivated>,
+) {
+    for (entity, interaction, button) in interaction_query.iter() {
+        match interaction {
+            Interaction::Hovered => menu_focus.order = button.order,
+            Interaction::Clicked => {
+                menu_focus.order = button.order;
+                activated_event_writer.send(ButtonActivated { entity });
+            }
+            Interaction::None => {}
+        }
+    }
+}
+
+pub fn confirm_focused_button(
+    menu_input: Res<MenuInput>,
+    button_query: Query<(Entity, &MenuButton)>,
+    menu_focus: Res<MenuFocus>,
+    mut activated_event_writer: EventWriter<ButtonActivated>,
+) {
+    if !menu_input.confirm {
+        return;
+    }
+    if let Some((entity, _)) = button_query
+        .iter()
+        .find(|(_, button)| button.order == menu_focus.order)
+    {
+        activated_event_writer.send(ButtonActivated { entity });
+    }
+}
+
+pub fn style_menu_buttons(
+    mut button_query: Query<(
+        &MenuButton,
+        Option<&Interaction>,
+        &mut BackgroundColor,
+        &Children,
+    )>,
+    mut text_query: Query<&mut Text, With<MenuButtonText>>,
+    menu_focus: Res<MenuFocus>,
+) {
+    for (button, interaction, mut background, children) in button_query.iter_mut() {
+        let focused = button.order == menu_focus.order;
+        let color = match (focused, interaction) {
+            (true, Some(Interaction::Clicked)) => PRESSED_BUTTON_COLOR,
+            (true, _) => FOCUSED_BUTTON_COLOR,
+            (false, _) => BUTTON_COLOR,
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+        let text_color = if focused {
+            FOCUSED_BUTTON_TEXT_COLOR
+        } else {
+            BUTTON_TEXT_COLOR
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                if text.sections[0].style.color != text_color {
+                    text.sections[0].style.color = text_color;
+                }
+            }
+        }
+    }
+}
+
+/// Focusable buttons and menu input shared by every screen.
+pub struct WidgetPlugin;
+
+impl Plugin for WidgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuInput>()
+            .init_resource::<MenuFocus>()
+            .add_event::<ButtonActivated>()
+            .add_systems(
+                (
+                    read_menu_input,
+                    navigate_menu,
+                    point_at_buttons,
+                    confirm_focused_button,
+                )
+                    .chain()
+                    .in_set(MenuWidgetSet),
+            )
+            .add_system(style_menu_buttons.after(MenuWidgetSet));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::{keyboard::KeyboardInput, ButtonState, InputPlugin};
+
+    fn tap(app: &mut App, key_code: KeyCode) {
+        for state in [ButtonState::Pressed, ButtonState::Released] {
+            app.world.send_event(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key_code),
+                state,
+            });
+            app.update();
+        }
+    }
+
+    #[test]
+    fn focus_wraps_around_and_confirm_activates_the_focused_button() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(InputPlugin)
+            .add_plugin(WidgetPlugin);
+        let buttons: Vec<Entity> = (0..3)
+            .map(|order| app.world.spawn(MenuButton { order }).id())
+            .collect();
+        let focus = |app: &App| app.world.resource::<MenuFocus>().order;
+
+        tap(&mut app, KeyCode::Up);
+        assert_eq!(focus(&app), 2);
+        tap(&mut app, KeyCode::Down);
+        tap(&mut app, KeyCode::Down);
+        assert_eq!(focus(&app), 1);
+
+        app.world.resource_mut::<Events<ButtonActivated>>().clear();
+        for key_code in [KeyCode::W, KeyCode::S, KeyCode::Space] {
+            tap(&mut app, key_code);
+        }
+        assert_eq!(focus(&app), 1);
+        assert!(app.world.resource::<Events<ButtonActivated>>().is_empty());
+
+        tap(&mut app, KeyCode::Return);
+        let activated: Vec<Entity> = app
+            .world
+            .resource_mut::<Events<ButtonActivated>>()
+            .drain()
+            .map(|event| event.entity)
+            .collect();
+        assert_eq!(activated, vec![buttons[1]]);
+    }
+}